@@ -0,0 +1,683 @@
+// Headless command line interface, declared in the `cli` section of `tauri.conf.json`
+// Commands print to stdout, logs of the indexer and search go to stderr
+
+use std::{cmp::Reverse, path::PathBuf};
+
+use serde::Serialize;
+use serde_json::Value;
+use tauri::{api::cli::Matches, utils::config::CliConfig};
+
+use crate::{
+    config::{get_config_dir, Config},
+    errors::lp_error::LpError,
+    file_handler::{self, Project},
+    git::{status::GitHead, unsaved},
+    indexer::Index,
+    launcher, picker,
+    search::{self, SearchHit, SearchIndexUpdate},
+    search_model::{self, Model},
+    timestamp::Timestamp,
+};
+
+/// Matches explained by `--explain` without a `--path`
+const EXPLAIN_HITS: usize = 5;
+
+/// Search results printed without a `--limit`
+const SEARCH_LIMIT: usize = 20;
+
+/// Name of the function `shell-init` prints without a `--name`
+const SHELL_FUNCTION: &str = "lpcd";
+
+/// Whether `matches` ask for a command, instead of opening the app window
+pub fn is_command(matches: &Matches) -> bool {
+    matches.subcommand.is_some()
+        || matches.args.contains_key("help")
+        || matches.args.contains_key("version")
+        || string_arg(matches, "explain").is_some()
+}
+
+/// Whether `args` name a subcommand or flag of `cli`, so an error parsing
+/// them is reported instead of opening the app window
+/// Other arguments may be passed by the OS or a launcher, not the user
+pub fn names_command(cli: &CliConfig, args: &[String]) -> bool {
+    let Some(first) = args.first() else {
+        return false;
+    };
+
+    if cli
+        .subcommands()
+        .is_some_and(|commands| commands.contains_key(first.as_str()))
+    {
+        return true;
+    }
+
+    let flags = cli.args().map(|args| args.as_slice()).unwrap_or_default();
+
+    args.iter().any(|arg| {
+        if let Some(long) = arg.strip_prefix("--") {
+            let name = long.split('=').next().unwrap_or(long);
+
+            flags.iter().any(|flag| flag.name == name)
+        } else if let Some(short) = arg.strip_prefix('-') {
+            flags
+                .iter()
+                .any(|flag| flag.short.is_some_and(|c| short.starts_with(c)))
+        } else {
+            false
+        }
+    })
+}
+
+/// Runs the command in `matches`, returns the exit code
+pub fn run(matches: &Matches) -> i32 {
+    match run_command(matches) {
+        Ok(()) => 0,
+        Err(err) => {
+            eprintln!("ERROR: {err}");
+            1
+        }
+    }
+}
+
+fn run_command(matches: &Matches) -> Result<(), LpError> {
+    if let Some(help) = string_arg(matches, "help") {
+        println!("{help}");
+        return Ok(());
+    }
+
+    if matches.args.contains_key("version") {
+        println!("{}", env!("CARGO_PKG_VERSION"));
+        return Ok(());
+    }
+
+    if let Some(query) = string_arg(matches, "explain") {
+        return explain(&query, string_arg(matches, "path"));
+    }
+
+    let Some(command) = &matches.subcommand else {
+        return Ok(());
+    };
+
+    let args = &command.matches;
+    let json = flag(args, "json");
+
+    match command.name.as_str() {
+        "list" => list(args, json),
+        "pick" => pick(args),
+        "shell-init" => shell_init(args),
+        "search" => search(args, json),
+        "open" => open(args),
+        "reindex" => reindex(json),
+        "add-dir" => add_dir(args),
+        "remove-dir" => remove_dir(args),
+        "status" => status(json),
+        "unsaved" => unsaved(json),
+        name => Err(LpError::Error(format!("Unknown command {name}"))),
+    }
+}
+
+/// Config, index and search model, as the app loads them on start
+/// The model is not saved, only `reindex` writes it
+fn load() -> (Config, Index, Model) {
+    let config = Config::load();
+    let index = Index::load_or_default();
+    let (model, _) = search::read_search_index(&config, &index);
+
+    (config, index, model)
+}
+
+/// Projects matching the `filters` query, by `--sort`, at most `--limit`
+fn filtered_projects(args: &Matches) -> Result<Vec<SearchHit>, LpError> {
+    let (_, index, model) = load();
+
+    let filters = joined_arg(args, "filters").unwrap_or_default();
+
+    let mut hits = search::search_projects(&filters, &model, &index)?.projects;
+
+    match string_arg(args, "sort").as_deref() {
+        Some("name") => hits.sort_by_key(|hit| hit.project.name.to_lowercase()),
+        Some("type") => hits.sort_by_key(|hit| format!("{:?}", hit.project.project_type)),
+        _ => hits.sort_by_key(|hit| Reverse(hit.project.last_modified)),
+    }
+
+    if let Some(limit) = limit(args)? {
+        hits.truncate(limit);
+    }
+
+    search::add_snippets(&mut hits, &model.lexer);
+
+    Ok(hits)
+}
+
+fn list(args: &Matches, json: bool) -> Result<(), LpError> {
+    let hits = filtered_projects(args)?;
+
+    if json {
+        let projects: Vec<&Project> = hits.iter().map(|hit| &hit.project).collect();
+
+        return print_json(&projects);
+    }
+
+    print_hits(&hits, false);
+
+    Ok(())
+}
+
+/// A line per project from `--template`, for pickers like fzf or rofi
+/// Lines end with NUL instead of a newline with `--print0`
+fn pick(args: &Matches) -> Result<(), LpError> {
+    let template = string_arg(args, "template").unwrap_or(String::from(picker::DEFAULT_TEMPLATE));
+
+    let end = if flag(args, "print0") { '\0' } else { '\n' };
+
+    let mut out = String::new();
+
+    for hit in filtered_projects(args)? {
+        out.push_str(&picker::render(&template, &hit.project)?);
+        out.push(end);
+    }
+
+    print!("{out}");
+
+    Ok(())
+}
+
+/// Prints a shell function that `cd`s into a project picked with fzf
+fn shell_init(args: &Matches) -> Result<(), LpError> {
+    let shell = string_arg(args, "shell").unwrap_or_default();
+    let name = string_arg(args, "name").unwrap_or(String::from(SHELL_FUNCTION));
+
+    let exe = std::env::current_exe()?;
+
+    print!(
+        "{}",
+        picker::shell_init(&shell, &name, &exe.to_string_lossy())?
+    );
+
+    Ok(())
+}
+
+/// Projects ranked for `query`, filters included
+fn search(args: &Matches, json: bool) -> Result<(), LpError> {
+    let (_, index, model) = load();
+
+    let query = joined_arg(args, "query").unwrap_or_default();
+
+    let mut response = search::search_projects(&query, &model, &index)?;
+
+    response
+        .projects
+        .truncate(limit(args)?.unwrap_or(SEARCH_LIMIT));
+
+    search::add_snippets(&mut response.projects, &model.lexer);
+
+    if json {
+        return print_json(&response);
+    }
+
+    print_hits(&response.projects, true);
+
+    if let Some(suggestion) = response.suggestion {
+        println!("\nDid you mean: {suggestion}");
+    }
+
+    Ok(())
+}
+
+/// Opens the project named or at `project` in the editor,
+/// or in the terminal or file manager
+fn open(args: &Matches) -> Result<(), LpError> {
+    let target = string_arg(args, "project").unwrap_or_default();
+
+    let config = Config::load();
+    let index = Index::load_or_default();
+
+    let project = find_project(&index, &target)?;
+
+    let target = launcher::Target::of(project, &target);
+
+    if let Some(startup) = string_arg(args, "run") {
+        return launcher::open_terminal(&config.launchers, &target, Some(&startup));
+    }
+
+    if flag(args, "terminal") {
+        return launcher::open_terminal(&config.launchers, &target, None);
+    }
+
+    if flag(args, "files") {
+        return launcher::open_file_manager(&target);
+    }
+
+    launcher::open(
+        &config.launchers,
+        &target,
+        string_arg(args, "with").as_deref(),
+    )
+}
+
+/// The project at `target`, or the one named `target` ignoring case
+fn find_project<'a>(index: &'a Index, target: &str) -> Result<&'a Project, LpError> {
+    let path = PathBuf::from(target);
+
+    if let Some(project) = index.find_project(&path) {
+        return Ok(project);
+    }
+
+    if let Some(project) = path
+        .canonicalize()
+        .ok()
+        .and_then(|p| index.find_project(&p))
+    {
+        return Ok(project);
+    }
+
+    let named: Vec<&Project> = index
+        .projects_map()
+        .values()
+        .filter(|p| p.name.eq_ignore_ascii_case(target))
+        .collect();
+
+    match named.as_slice() {
+        [project] => Ok(project),
+        [] => Err(LpError::Error(format!("No project named or at {target}"))),
+        _ => {
+            let paths: Vec<&str> = named.iter().map(|p| p.path.as_str()).collect();
+
+            Err(LpError::Error(format!(
+                "{target} names more than one project, use its path: {}",
+                paths.join(", ")
+            )))
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ReindexReport {
+    projects: usize,
+    pruned: usize,
+    search_index: SearchIndexUpdate,
+}
+
+/// Walks the project dirs, then updates the search index
+fn reindex(json: bool) -> Result<(), LpError> {
+    let config = Config::load();
+
+    let pruned = file_handler::initiate_search(&config)?;
+
+    let index = Index::load_or_default();
+
+    let (_, update) = search::load_search_index(&config, &index);
+
+    let report = ReindexReport {
+        projects: index.projects_map().len(),
+        pruned: pruned.len(),
+        search_index: update,
+    };
+
+    if json {
+        return print_json(&report);
+    }
+
+    println!(
+        "Indexed {} projects, pruned {}",
+        report.projects, report.pruned
+    );
+    println!(
+        "Search index: {} added, {} updated, {} removed",
+        report.search_index.added, report.search_index.updated, report.search_index.removed
+    );
+
+    Ok(())
+}
+
+fn add_dir(args: &Matches) -> Result<(), LpError> {
+    let path = string_arg(args, "path").unwrap_or_default();
+
+    let path = PathBuf::from(path).canonicalize()?;
+
+    let mut config = Config::load();
+
+    config.add_dir(path.to_string_lossy().to_string())?;
+    config.save()?;
+
+    println!(
+        "Added {}, run `reindex` to index its projects",
+        path.display()
+    );
+
+    Ok(())
+}
+
+fn remove_dir(args: &Matches) -> Result<(), LpError> {
+    let path = string_arg(args, "path").unwrap_or_default();
+
+    // a dir that was deleted can still be removed
+    let path = PathBuf::from(&path)
+        .canonicalize()
+        .unwrap_or(PathBuf::from(path));
+
+    let mut config = Config::load();
+
+    if !config.project_dirs.contains(&path) {
+        return Err(LpError::Error(format!(
+            "{} is not a project dir",
+            path.display()
+        )));
+    }
+
+    config.remove_dir(path.to_string_lossy().to_string())?;
+    config.save()?;
+
+    println!(
+        "Removed {}, its projects are pruned on the next `reindex`",
+        path.display()
+    );
+
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct Status {
+    config_dir: PathBuf,
+    project_dirs: Vec<PathBuf>,
+    projects: usize,
+    last_indexed: Timestamp,
+    /// Projects and workspace members in the search index
+    search_documents: usize,
+}
+
+fn status(json: bool) -> Result<(), LpError> {
+    let config = Config::load();
+    let index = Index::load_or_default();
+
+    let status = Status {
+        config_dir: get_config_dir(),
+        project_dirs: config.project_dirs,
+        projects: index.projects_map().len(),
+        last_indexed: index.last_indexed(),
+        search_documents: search_model::load_model().map_or(0, |model| model.len()),
+    };
+
+    if json {
+        return print_json(&status);
+    }
+
+    let dirs: Vec<String> = status
+        .project_dirs
+        .iter()
+        .map(|d| d.display().to_string())
+        .collect();
+
+    print_table(
+        &[],
+        vec![
+            row_of(&["Config dir", &status.config_dir.display().to_string()]),
+            row_of(&["Project dirs", &dirs.join(", ")]),
+            row_of(&["Projects", &status.projects.to_string()]),
+            row_of(&[
+                "Last indexed",
+                &format!("{} ago", age(status.last_indexed, Timestamp::now())),
+            ]),
+            row_of(&["Search documents", &status.search_documents.to_string()]),
+        ],
+    );
+
+    Ok(())
+}
+
+/// Git projects holding work that exists nowhere else, see `git::unsaved`
+fn unsaved(json: bool) -> Result<(), LpError> {
+    let index = Index::load_or_default();
+
+    let report = unsaved::unsaved_work_report(&index.projects());
+
+    if json {
+        return print_json(&report);
+    }
+
+    if report.is_empty() {
+        println!("No unsaved work found");
+        return Ok(());
+    }
+
+    let rows = report
+        .iter()
+        .map(|work| {
+            vec![
+                work.name.clone(),
+                work.uncommitted.to_string(),
+                work.unpushed_commits.to_string(),
+                work.branches_without_upstream.join(", "),
+                work.stash_count.to_string(),
+                String::from(if work.no_remote { "no" } else { "yes" }),
+                work.path.clone(),
+            ]
+        })
+        .collect();
+
+    print_table(
+        &[
+            "NAME",
+            "UNCOMMITTED",
+            "UNPUSHED",
+            "NO UPSTREAM",
+            "STASHES",
+            "REMOTE",
+            "PATH",
+        ],
+        rows,
+    );
+
+    Ok(())
+}
+
+/// Prints how the search model scores `path`, or the best matches of `query`
+fn explain(query: &str, path: Option<String>) -> Result<(), LpError> {
+    let (_, _, model) = load();
+
+    let query: Vec<char> = query.chars().collect();
+
+    let paths = match path {
+        Some(path) => vec![PathBuf::from(path)],
+        None => model
+            .search_hits(&query, EXPLAIN_HITS)
+            .into_iter()
+            .map(|hit| hit.path)
+            .collect(),
+    };
+
+    let explanations: Vec<_> = paths
+        .iter()
+        .filter_map(|path| model.explain(&query, path))
+        .collect();
+
+    print_json(&explanations)
+}
+
+fn print_json<T: Serialize>(value: &T) -> Result<(), LpError> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+
+    Ok(())
+}
+
+/// Table of `hits`, with their search score if `scored`
+fn print_hits(hits: &[SearchHit], scored: bool) {
+    let now = Timestamp::now();
+
+    let rows = hits
+        .iter()
+        .map(|hit| {
+            let project = &hit.project;
+
+            let mut row = Vec::new();
+
+            if scored {
+                row.push(hit.score.map_or(String::new(), |s| format!("{s:.2}")));
+            }
+
+            row.push(project.name.clone());
+            row.push(format!("{:?}", project.project_type));
+            row.push(age(project.last_modified, now));
+            row.push(git_summary(project));
+            row.push(project.path.clone());
+
+            row
+        })
+        .collect();
+
+    let headers = ["SCORE", "NAME", "TYPE", "MODIFIED", "GIT", "PATH"];
+
+    print_table(if scored { &headers } else { &headers[1..] }, rows);
+}
+
+/// Branch or detached commit, `*` marks uncommitted changes
+fn git_summary(project: &Project) -> String {
+    let Some(status) = &project.git_status else {
+        return String::new();
+    };
+
+    let head = match &status.head {
+        GitHead::Branch(name) | GitHead::Unborn(name) => name,
+        GitHead::Detached(hash) => hash,
+    };
+
+    let dirty = status.staged + status.unstaged + status.untracked + status.conflicted > 0;
+
+    format!("{head}{}", if dirty { "*" } else { "" })
+}
+
+/// Time since `timestamp` in the units of `modified:` filters
+fn age(timestamp: Timestamp, now: Timestamp) -> String {
+    let hours = (now.as_millis() - timestamp.as_millis()).max(0) / 3_600_000;
+
+    match hours {
+        0 => String::from("<1h"),
+        1..=23 => format!("{hours}h"),
+        24..=167 => format!("{}d", hours / 24),
+        168..=719 => format!("{}w", hours / (24 * 7)),
+        720..=8759 => format!("{}m", hours / (24 * 30)),
+        _ => format!("{}y", hours / (24 * 365)),
+    }
+}
+
+/// Prints `rows` in columns padded to their widest cell
+fn print_table(headers: &[&str], rows: Vec<Vec<String>>) {
+    let mut lines: Vec<Vec<String>> = Vec::new();
+
+    if !headers.is_empty() {
+        lines.push(row_of(headers));
+    }
+
+    lines.extend(rows);
+
+    let columns = lines.iter().map(|l| l.len()).max().unwrap_or(0);
+
+    let widths: Vec<usize> = (0..columns)
+        .map(|i| {
+            lines
+                .iter()
+                .filter_map(|l| l.get(i))
+                .map(|cell| cell.chars().count())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+
+    for line in lines {
+        let cells: Vec<String> = line
+            .iter()
+            .zip(widths.iter())
+            .map(|(cell, width)| format!("{cell:<width$}"))
+            .collect();
+
+        println!("{}", cells.join("  ").trim_end());
+    }
+}
+
+fn row_of(cells: &[&str]) -> Vec<String> {
+    cells.iter().map(|c| c.to_string()).collect()
+}
+
+fn string_arg(matches: &Matches, name: &str) -> Option<String> {
+    matches.args.get(name)?.value.as_str().map(String::from)
+}
+
+fn flag(matches: &Matches, name: &str) -> bool {
+    matches
+        .args
+        .get(name)
+        .is_some_and(|arg| arg.value == Value::Bool(true))
+}
+
+/// Values of an arg taking many, joined by spaces
+fn joined_arg(matches: &Matches, name: &str) -> Option<String> {
+    match &matches.args.get(name)?.value {
+        Value::Array(values) => {
+            let values: Vec<&str> = values.iter().filter_map(|v| v.as_str()).collect();
+
+            Some(values.join(" "))
+        }
+        Value::String(value) => Some(value.clone()),
+        _ => None,
+    }
+}
+
+fn limit(matches: &Matches) -> Result<Option<usize>, LpError> {
+    string_arg(matches, "limit")
+        .map(|limit| {
+            limit
+                .parse()
+                .map_err(|_| LpError::Error(format!("Invalid --limit {limit}")))
+        })
+        .transpose()
+}
+
+#[cfg(test)]
+mod cli_tests {
+    use tauri::utils::config::CliConfig;
+
+    use super::{age, names_command};
+    use crate::timestamp::Timestamp;
+
+    #[test]
+    fn it_should_print_ages_in_filter_units() {
+        let now = Timestamp::from_millis(1_000 * 3600 * 24 * 1000);
+
+        let ago = |hours: i64| {
+            age(
+                Timestamp::from_millis(now.as_millis() - hours * 3_600_000),
+                now,
+            )
+        };
+
+        assert_eq!(ago(0), "<1h");
+        assert_eq!(ago(5), "5h");
+        assert_eq!(ago(24 * 3), "3d");
+        assert_eq!(ago(24 * 14), "2w");
+        assert_eq!(ago(24 * 60), "2m");
+        assert_eq!(ago(24 * 800), "2y");
+    }
+
+    #[test]
+    fn it_should_only_take_known_commands_and_flags_as_commands() {
+        let cli: CliConfig = serde_json::from_value(serde_json::json!({
+            "args": [{ "name": "explain", "takesValue": true }],
+            "subcommands": { "list": { "args": [{ "name": "json" }] } }
+        }))
+        .unwrap();
+
+        let names = |args: &[&str]| {
+            let args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+            names_command(&cli, &args)
+        };
+
+        assert!(names(&["list", "--bogus"]));
+        assert!(names(&["--explain"]));
+        assert!(names(&["--explain=parser", "--bogus"]));
+
+        assert!(!names(&[]));
+        assert!(!names(&["-psn_0_12345"]));
+        assert!(!names(&["lsit"]));
+        assert!(!names(&["--bogus"]));
+    }
+}
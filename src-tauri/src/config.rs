@@ -8,7 +8,12 @@ use std::{
 
 use serde::{Deserialize, Serialize};
 
-use crate::errors::{config_error::ConfigError, lp_error::LpError};
+use crate::{
+    errors::{config_error::ConfigError, lp_error::LpError},
+    launcher::LauncherConfig,
+    lexer::LexerConfig,
+    search_model::Bm25Params,
+};
 
 /// Returns path for LP_CONFIG directory
 pub fn get_config_dir() -> PathBuf {
@@ -32,12 +37,39 @@ pub struct Config {
     /// List of directory, that would be searched
     /// for different projects
     pub project_dirs: Vec<PathBuf>,
+
+    /// Globs for directories to skip while walking `project_dirs`,
+    /// matched against the full path, e.g. `**/vendor`
+    #[serde(default)]
+    pub exclude_globs: Vec<String>,
+
+    /// Globs for directories that are always walked, even if they are
+    /// excluded by `exclude_globs` or ignore files
+    #[serde(default)]
+    pub include_globs: Vec<String>,
+
+    /// Field weights and normalization for search ranking
+    #[serde(default)]
+    pub search_params: Bm25Params,
+
+    /// Token pipeline for search, changes apply on the next re-index
+    #[serde(default)]
+    pub lexer: LexerConfig,
+
+    /// Editors projects are opened with, on top of the built in ones
+    #[serde(default)]
+    pub launchers: LauncherConfig,
 }
 
 impl Config {
     pub fn clone(&self) -> Config {
         Config {
             project_dirs: self.project_dirs.clone(),
+            exclude_globs: self.exclude_globs.clone(),
+            include_globs: self.include_globs.clone(),
+            search_params: self.search_params.clone(),
+            lexer: self.lexer.clone(),
+            launchers: self.launchers.clone(),
         }
     }
 
@@ -49,7 +81,7 @@ impl Config {
 
         path.push(PathBuf::from("lp.config.json"));
 
-        println!("Loading config from {path:?}");
+        eprintln!("Loading config from {path:?}");
 
         let file = OpenOptions::new()
             .create(true)
@@ -69,12 +101,24 @@ impl Config {
 
         let dirs = config.project_dirs;
 
-        Config { project_dirs: dirs }
+        Config {
+            project_dirs: dirs,
+            exclude_globs: config.exclude_globs,
+            include_globs: config.include_globs,
+            search_params: config.search_params,
+            lexer: config.lexer,
+            launchers: config.launchers,
+        }
     }
 
     pub fn new() -> Self {
         Config {
             project_dirs: Vec::new(),
+            exclude_globs: Vec::new(),
+            include_globs: Vec::new(),
+            search_params: Bm25Params::default(),
+            lexer: LexerConfig::default(),
+            launchers: LauncherConfig::default(),
         }
     }
 
@@ -90,7 +134,7 @@ impl Config {
 
         self.project_dirs.push(path.into());
 
-        println!("[config.add_dir] path added");
+        eprintln!("[config.add_dir] path added");
         Ok(())
     }
 
@@ -112,7 +156,7 @@ impl Config {
 
         std::fs::write(&path, &config_json)?;
 
-        println!("config saved at {path:?}", path = path.clone());
+        eprintln!("config saved at {path:?}", path = path.clone());
         Ok(())
     }
 }
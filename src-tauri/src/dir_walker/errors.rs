@@ -52,3 +52,9 @@ impl From<serde_yaml::Error> for LpError {
         Self::YamlError(value)
     }
 }
+
+impl From<roxmltree::Error> for LpError {
+    fn from(value: roxmltree::Error) -> Self {
+        Self::XmlError(value)
+    }
+}
@@ -1,4 +1,5 @@
 pub mod errors;
+pub mod walk_filter;
 pub mod walker;
 
 // cargo (rust), react (), next, svelte, sveltekit
@@ -0,0 +1,164 @@
+use std::{path::Path, sync::Arc};
+
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use ignore::{
+    gitignore::{Gitignore, GitignoreBuilder},
+    Match,
+};
+
+use crate::config::Config;
+
+/// Directory names that are never walked
+const EXCLUDE_DIRS: [&str; 5] = [".git", "node_modules", "target", ".vscode", "venv"];
+
+/// Ignore files honored in every walked directory
+const IGNORE_FILES: [&str; 2] = [".gitignore", ".ignore"];
+
+/// Ignore matchers of the ancestors of a directory, outermost first
+pub type IgnoreStack = Arc<Vec<Gitignore>>;
+
+/// Decides which directories the walker descends into
+/// * `include_globs` from config always win
+/// * then the built in `EXCLUDE_DIRS` and `exclude_globs` from config
+/// * then `.gitignore`/`.ignore` files, innermost first
+/// * then the global git excludes file (`core.excludesFile`)
+pub struct WalkFilter {
+    includes: GlobSet,
+    excludes: GlobSet,
+    global: Gitignore,
+}
+
+impl WalkFilter {
+    pub fn new(config: &Config) -> Self {
+        let (global, err) = Gitignore::global();
+
+        if let Some(err) = err {
+            eprintln!("ERROR: reading global ignore file : {err}");
+        }
+
+        WalkFilter {
+            includes: build_glob_set(&config.include_globs),
+            excludes: build_glob_set(&config.exclude_globs),
+            global,
+        }
+    }
+
+    /// Returns `stack` extended with the ignore files found in `dir`
+    /// A nested git repository starts a fresh stack, as its parent's
+    /// ignore files don't apply to it
+    pub fn stack_for(&self, dir: &Path, stack: &IgnoreStack) -> IgnoreStack {
+        let ignore_files: Vec<_> = IGNORE_FILES
+            .iter()
+            .map(|name| dir.join(name))
+            .filter(|file| file.is_file())
+            .collect();
+
+        let is_repo_root = dir.join(".git").exists();
+
+        if ignore_files.is_empty() && !is_repo_root {
+            return Arc::clone(stack);
+        }
+
+        let mut matchers = if is_repo_root {
+            Vec::new()
+        } else {
+            stack.as_ref().clone()
+        };
+
+        if !ignore_files.is_empty() {
+            let mut builder = GitignoreBuilder::new(dir);
+
+            for file in ignore_files {
+                if let Some(err) = builder.add(&file) {
+                    eprintln!("ERROR: reading ignore file {file:?} : {err}");
+                }
+            }
+
+            match builder.build() {
+                Ok(matcher) => matchers.push(matcher),
+                Err(err) => eprintln!("ERROR: building ignore matcher for {dir:?} : {err}"),
+            }
+        }
+
+        Arc::new(matchers)
+    }
+
+    /// Whether the directory `dir` should be skipped
+    /// `stack` holds the ignore matchers of `dir`'s ancestors
+    pub fn is_excluded(&self, dir: &Path, stack: &IgnoreStack) -> bool {
+        if self.includes.is_match(dir) {
+            return false;
+        }
+
+        let name = dir.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+
+        if EXCLUDE_DIRS.contains(&name) || self.excludes.is_match(dir) {
+            return true;
+        }
+
+        for matcher in stack.iter().rev() {
+            match matcher.matched(dir, true) {
+                Match::Ignore(_) => return true,
+                Match::Whitelist(_) => return false,
+                Match::None => {}
+            }
+        }
+
+        self.global.matched(dir, true).is_ignore()
+    }
+}
+
+/// Invalid globs are reported and skipped
+fn build_glob_set(globs: &[String]) -> GlobSet {
+    let mut builder = GlobSetBuilder::new();
+
+    for glob in globs {
+        match Glob::new(glob) {
+            Ok(glob) => {
+                builder.add(glob);
+            }
+            Err(err) => eprintln!("ERROR: invalid glob {glob:?} : {err}"),
+        }
+    }
+
+    builder.build().unwrap_or_else(|err| {
+        eprintln!("ERROR: building glob set : {err}");
+        GlobSet::empty()
+    })
+}
+
+#[cfg(test)]
+mod walk_filter_tests {
+    use std::sync::Arc;
+
+    use super::WalkFilter;
+    use crate::config::Config;
+
+    #[test]
+    fn it_should_honor_ignore_files_and_config_globs() {
+        let root = std::env::temp_dir().join("lp_walk_filter_test");
+        let _ = std::fs::remove_dir_all(&root);
+
+        for dir in ["src/app", "build", "vendor", "keep-vendor", "node_modules"] {
+            std::fs::create_dir_all(root.join(dir)).unwrap();
+        }
+
+        std::fs::write(root.join(".gitignore"), "build/\n").unwrap();
+
+        let mut config = Config::new();
+        config.exclude_globs = vec![String::from("**/*vendor")];
+        config.include_globs = vec![String::from("**/keep-vendor")];
+
+        let filter = WalkFilter::new(&config);
+
+        let stack = filter.stack_for(&root, &Arc::new(Vec::new()));
+
+        assert!(!filter.is_excluded(&root.join("src"), &stack));
+        assert!(filter.is_excluded(&root.join("build"), &stack));
+        assert!(filter.is_excluded(&root.join("vendor"), &stack));
+        assert!(!filter.is_excluded(&root.join("keep-vendor"), &stack));
+        assert!(filter.is_excluded(&root.join("node_modules"), &stack));
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
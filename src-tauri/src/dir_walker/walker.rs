@@ -2,7 +2,7 @@ use std::{
     collections::HashMap,
     fs::{self, File, OpenOptions},
     io::Write,
-    path::PathBuf,
+    path::{Path, PathBuf},
     sync::{Arc, Mutex},
     time::SystemTime,
 };
@@ -11,16 +11,24 @@ use crossbeam_channel::Sender;
 
 use crate::{
     config::Config,
+    dir_walker::walk_filter::{IgnoreStack, WalkFilter},
     errors::lp_error::LpError,
-    file_handler::{Project, ProjectType},
+    file_handler::{
+        GoModule, GoRequire, JvmBuild, JvmBuildTool, Project, ProjectDetails, ProjectType,
+        PythonPackageManager, PythonProject, WorkspaceMember,
+    },
+    git::status::get_git_status,
     indexer::Index,
+    tasks::discover,
+    timestamp::Timestamp,
 };
 
-struct Job(PathBuf, usize, Sender<Job>);
+struct Job(PathBuf, usize, Sender<Job>, IgnoreStack);
 
-use toml::Table;
+/// Depth below each of `project_dirs` that is walked for projects
+pub const MAX_DEPTH: usize = 4;
 
-const EXCLUDE_DIRS: [&str; 6] = [".git", "node_modules", "target", ".vscode", "src", "venv"];
+use toml::Table;
 
 ///  Entry point to analysis
 pub fn analyze_all_dirs(
@@ -29,16 +37,18 @@ pub fn analyze_all_dirs(
     max_depth: usize,
     index: Arc<Mutex<Index>>,
 ) -> Vec<Project> {
-    println!("Using {threads} for analyzing dirs");
+    eprintln!("Using {threads} for analyzing dirs");
     {
         let (job_sender, job_receiver) = crossbeam_channel::unbounded::<Job>();
 
         let (result_sender, result_receiver) = crossbeam_channel::unbounded::<Project>();
         let cloned_index = Arc::clone(&index);
+        let filter = Arc::new(WalkFilter::new(config));
         (0..threads)
             .map(|_| (job_receiver.clone(), result_sender.clone()))
             .for_each(|(jr, rs)| {
                 let clone = Arc::clone(&cloned_index);
+                let filter = Arc::clone(&filter);
                 std::thread::spawn(move || {
                     jr.into_iter().for_each(|job| {
                         scan_dir(
@@ -48,6 +58,8 @@ pub fn analyze_all_dirs(
                             max_depth,
                             job.2,
                             rs.clone(),
+                            &filter,
+                            &job.3,
                         )
                     })
                 });
@@ -55,7 +67,12 @@ pub fn analyze_all_dirs(
 
         config.project_dirs.iter().for_each(|p| {
             job_sender
-                .send(Job(p.to_path_buf(), 0, job_sender.clone()))
+                .send(Job(
+                    p.to_path_buf(),
+                    0,
+                    job_sender.clone(),
+                    Arc::new(Vec::new()),
+                ))
                 .unwrap();
         });
 
@@ -65,6 +82,57 @@ pub fn analyze_all_dirs(
     .collect()
 }
 
+/// Detect the project at `dir` alone, without walking its sub directories
+pub fn analyze_dir(dir: &PathBuf) -> Option<Project> {
+    let files: Vec<PathBuf> = dir
+        .read_dir()
+        .ok()?
+        .filter_map(|it| it.ok().map(|it| it.path()))
+        .filter(|it| !it.is_dir())
+        .collect();
+
+    let last_modified = dir
+        .metadata()
+        .and_then(|meta| meta.modified())
+        .unwrap_or(SystemTime::now());
+
+    get_relevant_project(&files, dir, last_modified)
+}
+
+/// Files that mark a directory as a project, by priority
+/// A dir with several is the type of the first, then `requirements*.txt`
+const PROJECT_IDENTIFIERS: [&str; 18] = [
+    "Cargo.toml",
+    "package.json",
+    "pubspec.yaml",
+    "go.mod",
+    "go.work",
+    "main.go",
+    "pyproject.toml",
+    "setup.cfg",
+    "setup.py",
+    "main.py",
+    "pom.xml",
+    "build.gradle",
+    "build.gradle.kts",
+    "settings.gradle",
+    "settings.gradle.kts",
+    "next.config.js",
+    "svelte.config.json",
+    "angular.json",
+];
+
+/// Whether a file with `name` at the top of a project is part of its details,
+/// a project identifier or doc file
+pub(crate) fn is_project_file(name: &str) -> bool {
+    let lowercase = name.to_lowercase();
+
+    PROJECT_IDENTIFIERS.contains(&name)
+        || is_requirements_file(name)
+        || lowercase == "readme.md"
+        || lowercase == "doc.md"
+}
+
 /// Get Relevant Details about a project if any
 /// `files` : Array of file paths in `dir`
 fn get_relevant_project(
@@ -72,20 +140,10 @@ fn get_relevant_project(
     dir: &PathBuf,
     last_modified: SystemTime,
 ) -> Option<Project> {
-    const PROJECT_IDENTIFIERS: [&str; 8] = [
-        "Cargo.toml",
-        "package.json",
-        "pubspec.yaml",
-        "main.go",
-        "main.py",
-        "next.config.js",
-        "svelte.config.json",
-        "angular.json",
-    ];
-
     let len = files.len();
 
     let mut id_file: Option<&PathBuf> = None;
+    let mut id_priority = usize::MAX;
 
     // Look for doc file, right now only README.md or DOC.md
     //
@@ -96,8 +154,15 @@ fn get_relevant_project(
     for i in 0..len {
         if let Some(name) = files[i].file_name() {
             let name = name.to_str().unwrap();
-            if PROJECT_IDENTIFIERS.contains(&name) && id_file.is_none() {
+            let priority = match PROJECT_IDENTIFIERS.iter().position(|id| *id == name) {
+                Some(position) => Some(position),
+                None => is_requirements_file(name).then_some(PROJECT_IDENTIFIERS.len()),
+            };
+
+            // `read_dir` order is arbitrary, keep the highest priority one
+            if let Some(priority) = priority.filter(|p| *p < id_priority) {
                 id_file = Some(&files[i]);
+                id_priority = priority;
             }
 
             if name.to_lowercase() == "readme.md" || name.to_lowercase() == "doc.md" {
@@ -128,6 +193,21 @@ fn get_relevant_project(
                 "pubspec.yaml" => {
                     project = flutter_project(id_file, dir).ok();
                 }
+                "go.mod" | "go.work" | "main.go" => {
+                    project = go_project(dir).ok();
+                }
+                "pyproject.toml" | "setup.cfg" | "setup.py" | "main.py" => {
+                    project = python_project(files, dir).ok();
+                }
+                "pom.xml" => {
+                    project = maven_project(id_file, dir).ok();
+                }
+                "build.gradle" | "build.gradle.kts" | "settings.gradle" | "settings.gradle.kts" => {
+                    project = gradle_project(files, dir).ok();
+                }
+                name if is_requirements_file(name) => {
+                    project = python_project(files, dir).ok();
+                }
                 _ => {}
             }
         }
@@ -136,13 +216,19 @@ fn get_relevant_project(
     if project.is_some() {
         let remotes = get_git_remotes(dir);
 
+        let git_status = get_git_status(dir);
+
         let language_map = get_project_language_map(dir);
 
+        let tasks = discover::discover(dir);
+
         project = project.map(|mut p| {
             p.git = remotes;
+            p.git_status = git_status;
             p.language_map = language_map;
-            p.last_modified = last_modified;
+            p.last_modified = Timestamp::from(last_modified);
             p.documentation_file = doc_file;
+            p.tasks = tasks;
             p
         });
     }
@@ -165,6 +251,8 @@ fn cargo_project(cargo_path: &PathBuf, project_dir: &PathBuf) -> Result<Project,
 
     let mut project_desc = None;
 
+    let mut members = Vec::new();
+
     if let Ok(parsed) = toml::from_str::<Table>(&toml_content)
         .map_err(|err| eprintln!("ERROR: parsing toml content : {err}"))
     {
@@ -173,16 +261,117 @@ fn cargo_project(cargo_path: &PathBuf, project_dir: &PathBuf) -> Result<Project,
                 project_name = name.as_str().unwrap().to_string();
             }
 
-            project_desc = package.get("description").map(|d| d.to_string());
+            // `description.workspace = true` is not a string
+            project_desc = package
+                .get("description")
+                .and_then(|d| d.as_str())
+                .map(|d| d.to_string());
+        }
+
+        if let Some(workspace) = parsed.get("workspace") {
+            let globs = |key: &str| -> Vec<String> {
+                workspace
+                    .get(key)
+                    .and_then(|m| m.as_array())
+                    .map(|m| {
+                        m.iter()
+                            .filter_map(|m| m.as_str())
+                            .map(|m| m.to_string())
+                            .collect()
+                    })
+                    .unwrap_or_default()
+            };
+
+            members = workspace_member_dirs(
+                project_dir,
+                &globs("members"),
+                &globs("exclude"),
+                "Cargo.toml",
+            )
+            .iter()
+            .filter_map(|dir| cargo_project(&dir.join("Cargo.toml"), dir).ok())
+            .map(workspace_member)
+            .collect();
         }
     }
 
-    Ok(Project::base(
+    let mut project = Project::base(
         project_name,
         project_dir.to_str().unwrap().to_string(),
         project_desc,
         ProjectType::Rust,
-    ))
+    );
+
+    project.members = members;
+
+    Ok(project)
+}
+
+/// Directories matched by workspace member `patterns`, relative to `root`
+/// Directories matched by `excludes`, or by patterns starting with `!`,
+/// and directories without a `manifest` file are left out
+fn workspace_member_dirs(
+    root: &PathBuf,
+    patterns: &[String],
+    excludes: &[String],
+    manifest: &str,
+) -> Vec<PathBuf> {
+    let expand = |pattern: &str| -> Vec<PathBuf> {
+        let pattern = root.join(pattern.trim_end_matches('/'));
+
+        match glob::glob(&pattern.to_string_lossy()) {
+            Ok(paths) => paths.filter_map(|p| p.ok()).collect(),
+            Err(err) => {
+                eprintln!("ERROR: invalid workspace glob {pattern:?} : {err}");
+                Vec::new()
+            }
+        }
+    };
+
+    let excluded: Vec<PathBuf> = excludes
+        .iter()
+        .map(|e| e.as_str())
+        .chain(patterns.iter().filter_map(|p| p.strip_prefix('!')))
+        .flat_map(expand)
+        .collect();
+
+    let mut dirs: Vec<PathBuf> = patterns
+        .iter()
+        .filter(|p| !p.starts_with('!'))
+        .flat_map(|p| expand(p))
+        .filter(|dir| dir != root && dir.join(manifest).is_file())
+        .filter(|dir| !excluded.contains(dir))
+        .collect();
+
+    dirs.sort();
+    dirs.dedup();
+
+    dirs
+}
+
+/// Turn a detected member project into a `WorkspaceMember`,
+/// picking up its own documentation file
+fn workspace_member(mut project: Project) -> WorkspaceMember {
+    project.documentation_file = find_doc_file(Path::new(&project.path));
+
+    project.into()
+}
+
+/// Look for doc file in `dir`, right now only README.md or DOC.md
+fn find_doc_file(dir: &Path) -> Option<String> {
+    dir.read_dir()
+        .ok()?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .find(|file| {
+            file.file_name()
+                .and_then(|name| name.to_str())
+                .map(|name| {
+                    let name = name.to_lowercase();
+                    name == "readme.md" || name == "doc.md"
+                })
+                .unwrap_or(false)
+        })
+        .map(|file| file.to_str().unwrap().to_string())
 }
 
 // Get details for a flutter project
@@ -216,6 +405,730 @@ fn flutter_project(pubspec_file: &PathBuf, project_dir: &PathBuf) -> Result<Proj
     ))
 }
 
+/// Get details for a Go project
+/// Reads `go.mod` and `go.work` from `project_dir` if present,
+/// a directory with only a `main.go` is still a Go project
+fn go_project(project_dir: &PathBuf) -> Result<Project, LpError> {
+    let mut project_name = project_dir
+        .file_name()
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .to_string();
+
+    let go_mod_file = project_dir.join("go.mod");
+    let go_work_file = project_dir.join("go.work");
+
+    let mut module = GoModule::default();
+
+    if go_mod_file.exists() {
+        let go_mod_content = fs::read_to_string(&go_mod_file)?;
+
+        module = parse_go_mod(&go_mod_content);
+
+        if let Some(name) = go_module_name(&module.module) {
+            project_name = name;
+        }
+    }
+
+    if go_work_file.exists() {
+        let go_work_content = fs::read_to_string(&go_work_file)?;
+
+        let (go_version, dirs) = parse_go_work(&go_work_content);
+
+        if module.go_version.is_none() {
+            module.go_version = go_version;
+        }
+
+        module.workspace = Some(dirs);
+    }
+
+    let mut project = Project::base(
+        project_name,
+        project_dir.to_str().unwrap().to_string(),
+        None,
+        ProjectType::Go,
+    );
+
+    project.details = Some(ProjectDetails::Go(module));
+
+    Ok(project)
+}
+
+/// Strips `//` comments and surrounding quotes from a go.mod/go.work line
+fn go_directive_line(line: &str) -> (&str, bool) {
+    let (code, comment) = match line.find("//") {
+        Some(i) => (&line[..i], Some(&line[i + 2..])),
+        None => (line, None),
+    };
+
+    let indirect = comment.map(|c| c.trim() == "indirect").unwrap_or(false);
+
+    (code.trim(), indirect)
+}
+
+fn go_unquote(value: &str) -> String {
+    value
+        .trim()
+        .trim_matches(|c| c == '"' || c == '`')
+        .to_string()
+}
+
+/// Parse contents of a `go.mod` file
+/// Only the `module`, `go` and `require` directives are read
+pub(crate) fn parse_go_mod(content: &str) -> GoModule {
+    let mut module = GoModule::default();
+
+    let mut in_require_block = false;
+    let mut in_other_block = false;
+
+    for line in content.lines() {
+        let (line, indirect) = go_directive_line(line);
+
+        if line.is_empty() {
+            continue;
+        }
+
+        if in_require_block || in_other_block {
+            if line == ")" {
+                in_require_block = false;
+                in_other_block = false;
+            } else if in_require_block && !indirect {
+                if let Some(require) = go_require(line) {
+                    module.requires.push(require);
+                }
+            }
+
+            continue;
+        }
+
+        let (directive, rest) = match line.split_once(char::is_whitespace) {
+            Some((directive, rest)) => (directive, rest.trim()),
+            None => (line, ""),
+        };
+
+        match directive {
+            "module" => module.module = go_unquote(rest),
+            "go" => module.go_version = Some(rest.to_string()),
+            "require" => {
+                if rest == "(" {
+                    in_require_block = true;
+                } else if !indirect {
+                    if let Some(require) = go_require(rest) {
+                        module.requires.push(require);
+                    }
+                }
+            }
+            _ => {
+                // replace, exclude, retract blocks are skipped
+                if rest.ends_with('(') {
+                    in_other_block = true;
+                }
+            }
+        }
+    }
+
+    module
+}
+
+fn go_require(spec: &str) -> Option<GoRequire> {
+    let mut parts = spec.split_whitespace();
+
+    let path = go_unquote(parts.next()?);
+    let version = parts.next()?.to_string();
+
+    Some(GoRequire { path, version })
+}
+
+/// Parse contents of a `go.work` file
+/// Returns the go version and the directories in `use` directives
+pub(crate) fn parse_go_work(content: &str) -> (Option<String>, Vec<String>) {
+    let mut go_version = None;
+    let mut dirs = Vec::new();
+
+    let mut in_use_block = false;
+    let mut in_other_block = false;
+
+    for line in content.lines() {
+        let (line, _) = go_directive_line(line);
+
+        if line.is_empty() {
+            continue;
+        }
+
+        if in_use_block || in_other_block {
+            if line == ")" {
+                in_use_block = false;
+                in_other_block = false;
+            } else if in_use_block {
+                dirs.push(go_unquote(line));
+            }
+
+            continue;
+        }
+
+        let (directive, rest) = match line.split_once(char::is_whitespace) {
+            Some((directive, rest)) => (directive, rest.trim()),
+            None => (line, ""),
+        };
+
+        match directive {
+            "go" => go_version = Some(rest.to_string()),
+            "use" => {
+                if rest == "(" {
+                    in_use_block = true;
+                } else {
+                    dirs.push(go_unquote(rest));
+                }
+            }
+            _ => {
+                if rest.ends_with('(') {
+                    in_other_block = true;
+                }
+            }
+        }
+    }
+
+    (go_version, dirs)
+}
+
+/// Last meaningful segment of a module path,
+/// `github.com/org/service/v2` -> `service`
+fn go_module_name(module_path: &str) -> Option<String> {
+    module_path
+        .rsplit('/')
+        .find(|segment| {
+            let is_major_version = segment.len() > 1
+                && segment.starts_with('v')
+                && segment[1..].chars().all(|c| c.is_ascii_digit());
+
+            !segment.is_empty() && !is_major_version
+        })
+        .map(|segment| segment.to_string())
+}
+
+/// `requirements.txt`, `requirements-dev.txt`, `requirements_test.txt` etc.
+fn is_requirements_file(name: &str) -> bool {
+    name.starts_with("requirements") && name.ends_with(".txt")
+}
+
+/// Name, description and version of a python package
+/// as declared in one of its metadata files
+#[derive(Default, Debug)]
+struct PythonMetadata {
+    name: Option<String>,
+    description: Option<String>,
+    version: Option<String>,
+}
+
+impl PythonMetadata {
+    /// Fill fields that are still missing from `other`
+    fn merge(&mut self, other: PythonMetadata) {
+        self.name = self.name.take().or(other.name);
+        self.description = self.description.take().or(other.description);
+        self.version = self.version.take().or(other.version);
+    }
+}
+
+/// Get details for a Python project
+/// Prefers `pyproject.toml` (PEP 621, Poetry, Hatch, PDM) and falls back to
+/// `setup.cfg`, `setup.py` and `requirements*.txt`
+fn python_project(files: &Vec<PathBuf>, project_dir: &PathBuf) -> Result<Project, LpError> {
+    let mut metadata = PythonMetadata::default();
+    let mut details = PythonProject::default();
+
+    let file_names: Vec<&str> = files
+        .iter()
+        .filter_map(|f| f.file_name())
+        .filter_map(|f| f.to_str())
+        .collect();
+
+    let has_file = |name: &str| file_names.contains(&name);
+
+    let mut pyproject_tools: Vec<String> = Vec::new();
+
+    if has_file("pyproject.toml") {
+        let content = fs::read_to_string(project_dir.join("pyproject.toml"))?;
+
+        if let Ok(parsed) = toml::from_str::<Table>(&content)
+            .map_err(|err| eprintln!("ERROR: parsing pyproject.toml : {err}"))
+        {
+            let (pyproject_metadata, pyproject_details) = parse_pyproject(&parsed);
+
+            metadata.merge(pyproject_metadata);
+            details = pyproject_details;
+
+            if let Some(tool) = parsed.get("tool").and_then(|t| t.as_table()) {
+                pyproject_tools = tool.keys().cloned().collect();
+            }
+        }
+    }
+
+    if has_file("setup.cfg") {
+        let content = fs::read_to_string(project_dir.join("setup.cfg"))?;
+
+        metadata.merge(parse_setup_cfg(&content));
+
+        if details.build_backend.is_none() {
+            details.build_backend = Some(String::from("setuptools"));
+        }
+    }
+
+    if has_file("setup.py") {
+        let content = fs::read_to_string(project_dir.join("setup.py"))?;
+
+        metadata.merge(parse_setup_py(&content));
+
+        if details.build_backend.is_none() {
+            details.build_backend = Some(String::from("setuptools"));
+        }
+    }
+
+    let mut requirement_files: Vec<&PathBuf> = files
+        .iter()
+        .filter(|f| {
+            f.file_name()
+                .and_then(|n| n.to_str())
+                .map(is_requirements_file)
+                .unwrap_or(false)
+        })
+        .collect();
+
+    requirement_files.sort();
+
+    if details.dependencies.is_empty() {
+        for file in requirement_files.iter() {
+            let content = fs::read_to_string(file)?;
+
+            for dep in parse_requirements(&content) {
+                if !details.dependencies.contains(&dep) {
+                    details.dependencies.push(dep);
+                }
+            }
+        }
+    }
+
+    let has_tool = |tool: &str| pyproject_tools.iter().any(|t| t == tool);
+    let backend = details.build_backend.clone().unwrap_or_default();
+
+    details.package_manager = if has_file("poetry.lock") || has_tool("poetry") {
+        Some(PythonPackageManager::Poetry)
+    } else if has_file("pdm.lock") || has_tool("pdm") {
+        Some(PythonPackageManager::Pdm)
+    } else if has_file("uv.lock") || has_tool("uv") {
+        Some(PythonPackageManager::Uv)
+    } else if has_tool("hatch") || backend.starts_with("hatchling") {
+        Some(PythonPackageManager::Hatch)
+    } else if has_file("Pipfile") {
+        Some(PythonPackageManager::Pipenv)
+    } else if !requirement_files.is_empty() || has_file("setup.py") || has_file("setup.cfg") {
+        Some(PythonPackageManager::Pip)
+    } else {
+        None
+    };
+
+    details.version = metadata.version;
+
+    let project_name = metadata.name.unwrap_or_else(|| {
+        project_dir
+            .file_name()
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_string()
+    });
+
+    let mut project = Project::base(
+        project_name,
+        project_dir.to_str().unwrap().to_string(),
+        metadata.description,
+        ProjectType::Python,
+    );
+
+    project.details = Some(ProjectDetails::Python(details));
+
+    Ok(project)
+}
+
+/// Read metadata from a parsed `pyproject.toml`
+/// `[project]` (PEP 621, used by Hatch and PDM) takes precedence over `[tool.poetry]`
+fn parse_pyproject(pyproject: &Table) -> (PythonMetadata, PythonProject) {
+    let mut metadata = PythonMetadata::default();
+    let mut details = PythonProject::default();
+
+    let str_field = |table: &toml::Value, key: &str| {
+        table
+            .get(key)
+            .and_then(|v| v.as_str())
+            .map(|v| v.to_string())
+    };
+
+    if let Some(project) = pyproject.get("project") {
+        metadata.name = str_field(project, "name");
+        metadata.description = str_field(project, "description");
+        metadata.version = str_field(project, "version");
+
+        if let Some(deps) = project.get("dependencies").and_then(|d| d.as_array()) {
+            details.dependencies = deps
+                .iter()
+                .filter_map(|d| d.as_str())
+                .filter_map(requirement_name)
+                .collect();
+        }
+    }
+
+    if let Some(poetry) = pyproject.get("tool").and_then(|t| t.get("poetry")) {
+        metadata.merge(PythonMetadata {
+            name: str_field(poetry, "name"),
+            description: str_field(poetry, "description"),
+            version: str_field(poetry, "version"),
+        });
+
+        if details.dependencies.is_empty() {
+            if let Some(deps) = poetry.get("dependencies").and_then(|d| d.as_table()) {
+                details.dependencies = deps
+                    .keys()
+                    .filter(|k| k.as_str() != "python")
+                    .cloned()
+                    .collect();
+            }
+        }
+    }
+
+    details.build_backend = pyproject
+        .get("build-system")
+        .and_then(|b| b.get("build-backend"))
+        .and_then(|b| b.as_str())
+        .map(|b| b.to_string());
+
+    (metadata, details)
+}
+
+/// Read `[metadata]` section of a `setup.cfg`
+fn parse_setup_cfg(content: &str) -> PythonMetadata {
+    let mut metadata = PythonMetadata::default();
+
+    let mut in_metadata = false;
+
+    for line in content.lines() {
+        let trimmed = line.trim();
+
+        if trimmed.starts_with('[') {
+            in_metadata = trimmed == "[metadata]";
+            continue;
+        }
+
+        // continuation lines are indented
+        if !in_metadata || line.starts_with(char::is_whitespace) {
+            continue;
+        }
+
+        if let Some((key, value)) = trimmed.split_once('=') {
+            let value = value.trim();
+
+            if value.is_empty() || value.starts_with("attr:") || value.starts_with("file:") {
+                continue;
+            }
+
+            let value = Some(value.to_string());
+
+            match key.trim() {
+                "name" => metadata.name = value,
+                "version" => metadata.version = value,
+                "description" | "summary" => metadata.description = value,
+                _ => {}
+            }
+        }
+    }
+
+    metadata
+}
+
+/// Read string literal keyword arguments of `setup(...)` in a `setup.py`
+/// Values computed at runtime are ignored
+fn parse_setup_py(content: &str) -> PythonMetadata {
+    PythonMetadata {
+        name: setup_py_kwarg(content, "name"),
+        description: setup_py_kwarg(content, "description"),
+        version: setup_py_kwarg(content, "version"),
+    }
+}
+
+fn setup_py_kwarg(content: &str, key: &str) -> Option<String> {
+    let mut search_from = 0;
+
+    while let Some(pos) = content[search_from..].find(key) {
+        let start = search_from + pos;
+        let end = start + key.len();
+        search_from = end;
+
+        let preceded_by_ident = content[..start]
+            .chars()
+            .last()
+            .map(|c| c.is_alphanumeric() || c == '_')
+            .unwrap_or(false);
+
+        if preceded_by_ident {
+            continue;
+        }
+
+        let rest = content[end..].trim_start();
+
+        let Some(rest) = rest.strip_prefix('=') else {
+            continue;
+        };
+
+        // `==` is a comparison, not a keyword argument
+        if rest.starts_with('=') {
+            continue;
+        }
+
+        let rest = rest.trim_start();
+
+        let quote = match rest.chars().next() {
+            Some(q) if q == '"' || q == '\'' => q,
+            _ => continue,
+        };
+
+        if let Some(close) = rest[1..].find(quote) {
+            return Some(rest[1..close + 1].to_string());
+        }
+    }
+
+    None
+}
+
+/// Names of the packages listed in a `requirements.txt`
+fn parse_requirements(content: &str) -> Vec<String> {
+    content
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or("").trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('-'))
+        .filter_map(requirement_name)
+        .collect()
+}
+
+/// Package name of a PEP 508 requirement, `requests[socks]>=2.0` -> `requests`
+fn requirement_name(spec: &str) -> Option<String> {
+    let name: String = spec
+        .trim()
+        .chars()
+        .take_while(|c| c.is_alphanumeric() || *c == '-' || *c == '_' || *c == '.')
+        .collect();
+
+    if name.is_empty() {
+        None
+    } else {
+        Some(name)
+    }
+}
+
+/// Get details for a Maven project
+/// A multi-module build is a single project listing its `<modules>`
+fn maven_project(pom_file: &PathBuf, project_dir: &PathBuf) -> Result<Project, LpError> {
+    let xml_content = fs::read_to_string(pom_file)?;
+
+    let (name, description, build, is_kotlin) = parse_pom(&xml_content)?;
+
+    let project_name = name.or(build.artifact_id.clone()).unwrap_or_else(|| {
+        project_dir
+            .file_name()
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_string()
+    });
+
+    let project_type = if is_kotlin || project_dir.join("src/main/kotlin").is_dir() {
+        ProjectType::Kotlin
+    } else {
+        ProjectType::Java
+    };
+
+    let mut project = Project::base(
+        project_name,
+        project_dir.to_str().unwrap().to_string(),
+        description,
+        project_type,
+    );
+
+    project.details = Some(ProjectDetails::Jvm(build));
+
+    Ok(project)
+}
+
+/// Parse a `pom.xml`
+/// Returns name, description, build details and whether kotlin plugin is used
+fn parse_pom(content: &str) -> Result<(Option<String>, Option<String>, JvmBuild, bool), LpError> {
+    let doc = roxmltree::Document::parse(content)?;
+
+    let root = doc.root_element();
+
+    let child_text = |node: roxmltree::Node, tag: &str| {
+        node.children()
+            .find(|c| c.has_tag_name(tag))
+            .and_then(|c| c.text())
+            .map(|t| t.trim().to_string())
+            .filter(|t| !t.is_empty())
+    };
+
+    let parent = root.children().find(|c| c.has_tag_name("parent"));
+
+    let mut build = JvmBuild {
+        build_tool: JvmBuildTool::Maven,
+        group_id: child_text(root, "groupId"),
+        artifact_id: child_text(root, "artifactId"),
+        version: child_text(root, "version"),
+        modules: Vec::new(),
+    };
+
+    // groupId and version are inherited from the parent pom when missing
+    if let Some(parent) = parent {
+        build.group_id = build.group_id.or(child_text(parent, "groupId"));
+        build.version = build.version.or(child_text(parent, "version"));
+    }
+
+    if let Some(modules) = root.children().find(|c| c.has_tag_name("modules")) {
+        build.modules = modules
+            .children()
+            .filter(|c| c.has_tag_name("module"))
+            .filter_map(|c| c.text())
+            .map(|t| t.trim().to_string())
+            .collect();
+    }
+
+    let is_kotlin = root
+        .descendants()
+        .filter(|n| n.has_tag_name("artifactId"))
+        .filter_map(|n| n.text())
+        .any(|t| t.trim() == "kotlin-maven-plugin");
+
+    Ok((
+        child_text(root, "name"),
+        child_text(root, "description"),
+        build,
+        is_kotlin,
+    ))
+}
+
+/// Get details for a Gradle project, groovy or kotlin dsl
+/// Modules are read from `include` in `settings.gradle(.kts)`,
+/// a multi-module build is a single project listing them
+fn gradle_project(files: &Vec<PathBuf>, project_dir: &PathBuf) -> Result<Project, LpError> {
+    let read_first = |names: [&str; 2]| -> Result<Option<String>, LpError> {
+        for name in names {
+            if files
+                .iter()
+                .any(|f| f.file_name().and_then(|f| f.to_str()) == Some(name))
+            {
+                return Ok(Some(fs::read_to_string(project_dir.join(name))?));
+            }
+        }
+
+        Ok(None)
+    };
+
+    let settings = read_first(["settings.gradle.kts", "settings.gradle"])?.unwrap_or_default();
+    let build_file = read_first(["build.gradle.kts", "build.gradle"])?.unwrap_or_default();
+
+    let project_name = gradle_property(&settings, "rootProject.name").unwrap_or_else(|| {
+        project_dir
+            .file_name()
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_string()
+    });
+
+    let build = JvmBuild {
+        build_tool: JvmBuildTool::Gradle,
+        group_id: gradle_property(&build_file, "group"),
+        artifact_id: Some(project_name.clone()),
+        version: gradle_property(&build_file, "version"),
+        modules: gradle_includes(&settings),
+    };
+
+    let is_kotlin = [
+        "org.jetbrains.kotlin",
+        "kotlin(\"",
+        "id 'kotlin",
+        "kotlin-android",
+    ]
+    .iter()
+    .any(|marker| build_file.contains(marker))
+        || project_dir.join("src/main/kotlin").is_dir();
+
+    let project_type = if is_kotlin {
+        ProjectType::Kotlin
+    } else {
+        ProjectType::Java
+    };
+
+    let mut project = Project::base(
+        project_name,
+        project_dir.to_str().unwrap().to_string(),
+        gradle_property(&build_file, "description"),
+        project_type,
+    );
+
+    project.details = Some(ProjectDetails::Jvm(build));
+
+    Ok(project)
+}
+
+/// String value assigned to `key` in a gradle script,
+/// handles `key = "value"`, `key 'value'` and `key("value")`
+fn gradle_property(content: &str, key: &str) -> Option<String> {
+    content.lines().find_map(|line| {
+        let rest = line.trim().strip_prefix(key)?;
+
+        if !rest.starts_with(|c: char| c.is_whitespace() || c == '=' || c == '(') {
+            return None;
+        }
+
+        let rest = rest
+            .trim_start()
+            .trim_start_matches(['=', '('])
+            .trim_start();
+
+        quoted_strings(rest).into_iter().next()
+    })
+}
+
+/// Modules from `include` statements in `settings.gradle(.kts)`
+/// `:libs:core` -> `libs/core`
+fn gradle_includes(settings: &str) -> Vec<String> {
+    settings
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("include"))
+        .filter(|rest| rest.starts_with(|c: char| c.is_whitespace() || c == '('))
+        .flat_map(quoted_strings)
+        .map(|module| module.trim_start_matches(':').replace(':', "/"))
+        .filter(|module| !module.is_empty())
+        .collect()
+}
+
+/// All single or double quoted string literals in `text`
+fn quoted_strings(text: &str) -> Vec<String> {
+    let mut strings = Vec::new();
+    let mut rest = text;
+
+    while let Some(start) = rest.find(['"', '\'']) {
+        let quote = rest[start..].chars().next().unwrap();
+        let after = &rest[start + 1..];
+
+        match after.find(quote) {
+            Some(end) => {
+                strings.push(after[..end].to_string());
+                rest = &after[end + 1..];
+            }
+            None => break,
+        }
+    }
+
+    strings
+}
+
 /// Get details for node/react/svelete or any Js project with package.json
 fn node_project(
     package_json_file: &PathBuf,
@@ -307,12 +1220,90 @@ fn node_project(
         }
     }
 
-    Ok(Project::base(
+    let mut project = Project::base(
         project_name,
         project_dir.to_str().unwrap().to_string(),
         project_desc,
         project_type,
-    ))
+    );
+
+    project.members = node_workspace_members(&package_json, project_dir);
+
+    Ok(project)
+}
+
+/// Members of a npm/yarn workspace (`workspaces` in package.json)
+/// or a pnpm workspace (`packages` in pnpm-workspace.yaml)
+fn node_workspace_members(
+    package_json: &serde_json::Value,
+    project_dir: &PathBuf,
+) -> Vec<WorkspaceMember> {
+    let to_globs = |value: Option<&serde_json::Value>| -> Vec<String> {
+        value
+            .and_then(|v| v.as_array())
+            .map(|v| {
+                v.iter()
+                    .filter_map(|g| g.as_str())
+                    .map(|g| g.to_string())
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+
+    // `workspaces` is either an array of globs, or yarn's `{ packages: [..] }`
+    let workspaces = package_json.get("workspaces");
+    let mut globs = to_globs(workspaces);
+
+    if globs.is_empty() {
+        globs = to_globs(workspaces.and_then(|w| w.get("packages")));
+    }
+
+    let pnpm_workspace = project_dir.join("pnpm-workspace.yaml");
+
+    // an unreadable pnpm workspace leaves the project with the members
+    // from package.json
+    if pnpm_workspace.is_file() {
+        match pnpm_workspace_globs(&pnpm_workspace) {
+            Ok(pnpm_globs) => globs.extend(pnpm_globs),
+            Err(err) => eprintln!("ERROR: reading {pnpm_workspace:?} : {err}"),
+        }
+    }
+
+    workspace_member_dirs(project_dir, &globs, &[], "package.json")
+        .iter()
+        .filter_map(|dir| {
+            let files: Vec<PathBuf> = dir
+                .read_dir()
+                .ok()?
+                .filter_map(|it| it.ok().map(|it| it.path()))
+                .filter(|it| it.is_file())
+                .collect();
+
+            node_project(&dir.join("package.json"), &files, dir).ok()
+        })
+        .map(workspace_member)
+        .collect()
+}
+
+/// `packages` globs of a pnpm-workspace.yaml
+fn pnpm_workspace_globs(pnpm_workspace: &Path) -> Result<Vec<String>, LpError> {
+    let yaml_content = fs::read_to_string(pnpm_workspace)?;
+
+    let pnpm: serde_yaml::Value = serde_yaml::from_str(&yaml_content)?;
+
+    let globs = pnpm
+        .get("packages")
+        .and_then(|p| p.as_sequence())
+        .map(|packages| {
+            packages
+                .iter()
+                .filter_map(|p| p.as_str())
+                .map(|p| p.to_string())
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Ok(globs)
 }
 
 /// Get a languge data map for a project
@@ -386,8 +1377,10 @@ fn scan_dir(
     max_depth: usize,
     job_sender: Sender<Job>,
     result_sender: Sender<Project>,
+    filter: &WalkFilter,
+    ignore_stack: &IgnoreStack,
 ) {
-    println!("Scanning {path:?} as depth {depth}");
+    eprintln!("Scanning {path:?} as depth {depth}");
 
     let dot_file = path
         .file_name()
@@ -443,21 +1436,31 @@ fn scan_dir(
                 let _ = file.write_fmt(format_args!("{path:?}\n"));
             }
         }
+    } else {
+        // up to date, its workspace members and build modules are not walked
+        // as they belong to it
+        found_project = true;
     }
 
     // println!("FOUND PROJECT {found_project} || Depth {depth}");
 
     if found_project == false && max_depth > depth {
-        println!("\n\nIterating Dirs for {path:?}");
-        for dir in dirs {
-            let filename = dir.file_name().unwrap_or_default().to_str().unwrap();
+        eprintln!("\n\nIterating Dirs for {path:?}");
+
+        let ignore_stack = filter.stack_for(path, ignore_stack);
 
-            if EXCLUDE_DIRS.contains(&filename) {
+        for dir in dirs {
+            if filter.is_excluded(&dir, &ignore_stack) {
                 continue;
             }
 
             job_sender
-                .send(Job(dir.to_path_buf(), depth + 1, job_sender.clone()))
+                .send(Job(
+                    dir.to_path_buf(),
+                    depth + 1,
+                    job_sender.clone(),
+                    Arc::clone(&ignore_stack),
+                ))
                 .unwrap();
         }
     } else {
@@ -467,7 +1470,97 @@ fn scan_dir(
 
 #[cfg(test)]
 mod walker_tests {
-    use super::flutter_project;
+    use std::{
+        path::{Path, PathBuf},
+        sync::{Arc, Mutex},
+    };
+
+    use super::{
+        analyze_all_dirs, flutter_project, get_relevant_project, go_module_name, gradle_includes,
+        gradle_property, parse_go_mod, parse_go_work, parse_pom, parse_pyproject,
+        parse_requirements, parse_setup_cfg, parse_setup_py, workspace_member_dirs,
+    };
+    use crate::{config::Config, file_handler::ProjectType, indexer::Index};
+
+    /// Paths of the projects indexed after walking `root` twice
+    fn walk_twice(root: &Path) -> Vec<PathBuf> {
+        let mut config = Config::new();
+        config.project_dirs = vec![root.to_path_buf()];
+
+        let index = Arc::new(Mutex::new(Index::default()));
+
+        analyze_all_dirs(&config, 2, 4, Arc::clone(&index));
+        analyze_all_dirs(&config, 2, 4, Arc::clone(&index));
+
+        let paths = index
+            .lock()
+            .unwrap()
+            .projects_map()
+            .keys()
+            .cloned()
+            .collect();
+
+        paths
+    }
+
+    #[test]
+    fn it_should_keep_build_modules_in_one_project_on_later_walks() {
+        let root = std::env::temp_dir().join("lp_walker_rewalk_modules_test");
+        let _ = std::fs::remove_dir_all(&root);
+
+        let maven = root.join("orders");
+        let gradle = root.join("shop");
+
+        for module in [maven.join("orders-api"), gradle.join("app")] {
+            std::fs::create_dir_all(module).unwrap();
+        }
+
+        std::fs::write(
+            maven.join("pom.xml"),
+            "<project><artifactId>orders</artifactId>\
+             <modules><module>orders-api</module></modules></project>",
+        )
+        .unwrap();
+        std::fs::write(
+            maven.join("orders-api/pom.xml"),
+            "<project><artifactId>orders-api</artifactId></project>",
+        )
+        .unwrap();
+        std::fs::write(
+            gradle.join("settings.gradle"),
+            "rootProject.name = 'shop'\ninclude ':app'\n",
+        )
+        .unwrap();
+        std::fs::write(gradle.join("app/build.gradle"), "plugins { id 'java' }\n").unwrap();
+
+        assert_eq!(walk_twice(&root), vec![maven, gradle]);
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn it_should_keep_workspace_members_grouped_on_later_walks() {
+        let root = std::env::temp_dir().join("lp_walker_rewalk_workspace_test");
+        let _ = std::fs::remove_dir_all(&root);
+
+        let workspace = root.join("workspace");
+
+        std::fs::create_dir_all(workspace.join("crates/core")).unwrap();
+        std::fs::write(
+            workspace.join("Cargo.toml"),
+            "[workspace]\nmembers = [\"crates/*\"]\n",
+        )
+        .unwrap();
+        std::fs::write(
+            workspace.join("crates/core/Cargo.toml"),
+            "[package]\nname = \"core\"\n",
+        )
+        .unwrap();
+
+        assert_eq!(walk_twice(&root), vec![workspace]);
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
 
     #[ignore]
     #[test]
@@ -507,4 +1600,244 @@ mod walker_tests {
 
         assert!(project.is_ok());
     }
+
+    #[test]
+    fn it_should_parse_go_mod() {
+        let content = r#"
+module github.com/acme/billing/v2
+
+go 1.21
+
+require github.com/google/uuid v1.3.0
+
+require (
+	github.com/gin-gonic/gin v1.9.1
+	golang.org/x/sys v0.8.0 // indirect
+)
+
+replace (
+	github.com/acme/lib => ../lib
+)
+"#;
+
+        let module = parse_go_mod(content);
+
+        assert_eq!(module.module, "github.com/acme/billing/v2");
+        assert_eq!(module.go_version.as_deref(), Some("1.21"));
+        assert_eq!(module.requires.len(), 2);
+        assert_eq!(module.requires[0].path, "github.com/google/uuid");
+        assert_eq!(module.requires[1].version, "v1.9.1");
+        assert_eq!(go_module_name(&module.module).as_deref(), Some("billing"));
+    }
+
+    #[test]
+    fn it_should_parse_go_work() {
+        let content = "go 1.22\n\nuse (\n\t./api\n\t./worker // jobs\n)\nuse ./tools\n";
+
+        let (go_version, dirs) = parse_go_work(content);
+
+        assert_eq!(go_version.as_deref(), Some("1.22"));
+        assert_eq!(dirs, vec!["./api", "./worker", "./tools"]);
+    }
+
+    #[test]
+    fn it_should_read_pep_621_and_poetry_pyproject() {
+        let pep_621 = r#"
+[project]
+name = "etl-jobs"
+description = "Nightly ETL"
+version = "0.4.0"
+dependencies = ["pandas>=2.0", "requests[socks]"]
+
+[build-system]
+requires = ["hatchling"]
+build-backend = "hatchling.build"
+"#;
+
+        let (metadata, details) = parse_pyproject(&toml::from_str(pep_621).unwrap());
+
+        assert_eq!(metadata.name.as_deref(), Some("etl-jobs"));
+        assert_eq!(metadata.version.as_deref(), Some("0.4.0"));
+        assert_eq!(details.build_backend.as_deref(), Some("hatchling.build"));
+        assert_eq!(details.dependencies, vec!["pandas", "requests"]);
+
+        let poetry = r#"
+[tool.poetry]
+name = "scraper"
+description = "Crawls things"
+version = "1.0.0"
+
+[tool.poetry.dependencies]
+python = "^3.11"
+httpx = "^0.25"
+"#;
+
+        let (metadata, details) = parse_pyproject(&toml::from_str(poetry).unwrap());
+
+        assert_eq!(metadata.name.as_deref(), Some("scraper"));
+        assert_eq!(metadata.description.as_deref(), Some("Crawls things"));
+        assert_eq!(details.dependencies, vec!["httpx"]);
+        assert!(details.build_backend.is_none());
+    }
+
+    #[test]
+    fn it_should_read_setup_cfg_setup_py_and_requirements() {
+        let setup_cfg = "[metadata]\nname = legacy-lib\nversion = attr: legacy.__version__\ndescription = Old but gold\n\n[options]\nname = nope\n";
+
+        let metadata = parse_setup_cfg(setup_cfg);
+
+        assert_eq!(metadata.name.as_deref(), Some("legacy-lib"));
+        assert_eq!(metadata.version, None);
+        assert_eq!(metadata.description.as_deref(), Some("Old but gold"));
+
+        let setup_py = r#"
+setup(
+    name="tool",
+    long_description=open("README.md").read(),
+    description='A tool',
+    version = "2.1",
+)
+"#;
+
+        let metadata = parse_setup_py(setup_py);
+
+        assert_eq!(metadata.name.as_deref(), Some("tool"));
+        assert_eq!(metadata.description.as_deref(), Some("A tool"));
+        assert_eq!(metadata.version.as_deref(), Some("2.1"));
+
+        let requirements =
+            "# pinned\nflask==3.0.0\n-r base.txt\n\nnumpy ; python_version > '3.8'\n";
+
+        assert_eq!(parse_requirements(requirements), vec!["flask", "numpy"]);
+    }
+
+    #[test]
+    fn it_should_parse_multi_module_pom() {
+        let pom = r#"<?xml version="1.0"?>
+<project xmlns="http://maven.apache.org/POM/4.0.0">
+  <parent>
+    <groupId>com.acme</groupId>
+    <artifactId>acme-parent</artifactId>
+    <version>3.1.0</version>
+  </parent>
+  <artifactId>orders</artifactId>
+  <description>Order service</description>
+  <modules>
+    <module>orders-api</module>
+    <module>orders-core</module>
+  </modules>
+  <build><plugins><plugin>
+    <artifactId>kotlin-maven-plugin</artifactId>
+  </plugin></plugins></build>
+</project>"#;
+
+        let (name, description, build, is_kotlin) = parse_pom(pom).unwrap();
+
+        assert_eq!(name, None);
+        assert_eq!(description.as_deref(), Some("Order service"));
+        assert_eq!(build.group_id.as_deref(), Some("com.acme"));
+        assert_eq!(build.artifact_id.as_deref(), Some("orders"));
+        assert_eq!(build.version.as_deref(), Some("3.1.0"));
+        assert_eq!(build.modules, vec!["orders-api", "orders-core"]);
+        assert!(is_kotlin);
+    }
+
+    #[test]
+    fn it_should_read_gradle_settings_and_build_files() {
+        let settings = "rootProject.name = \"shop\"\ninclude(\":app\", \":libs:core\")\ninclude ':feature'\nincludeBuild(\"build-logic\")\n";
+
+        assert_eq!(
+            gradle_property(settings, "rootProject.name").as_deref(),
+            Some("shop")
+        );
+        assert_eq!(
+            gradle_includes(settings),
+            vec!["app", "libs/core", "feature"]
+        );
+
+        let build = "group 'com.acme'\nversion = \"1.2.0\"\ngroupId = 'nope'\n";
+
+        assert_eq!(gradle_property(build, "group").as_deref(), Some("com.acme"));
+        assert_eq!(gradle_property(build, "version").as_deref(), Some("1.2.0"));
+    }
+
+    #[test]
+    fn it_should_resolve_workspace_member_globs() {
+        let root = std::env::temp_dir().join("lp_walker_workspace_test");
+        let _ = std::fs::remove_dir_all(&root);
+
+        for member in ["crates/core", "crates/cli", "crates/legacy", "crates/notes"] {
+            std::fs::create_dir_all(root.join(member)).unwrap();
+        }
+
+        for member in ["crates/core", "crates/cli", "crates/legacy"] {
+            std::fs::write(root.join(member).join("Cargo.toml"), "").unwrap();
+        }
+
+        let dirs = workspace_member_dirs(
+            &root,
+            &[String::from("crates/*"), String::from("!crates/cli")],
+            &[String::from("crates/legacy")],
+            "Cargo.toml",
+        );
+
+        assert_eq!(dirs, vec![root.join("crates/core")]);
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn it_should_keep_a_node_workspace_with_an_invalid_pnpm_workspace() {
+        let root = std::env::temp_dir().join("lp_walker_pnpm_invalid_test");
+        let _ = std::fs::remove_dir_all(&root);
+
+        std::fs::create_dir_all(root.join("packages/ui")).unwrap();
+
+        std::fs::write(
+            root.join("package.json"),
+            r#"{ "name": "shop", "workspaces": ["packages/*"] }"#,
+        )
+        .unwrap();
+        std::fs::write(root.join("packages/ui/package.json"), r#"{ "name": "ui" }"#).unwrap();
+        std::fs::write(root.join("pnpm-workspace.yaml"), "packages: [unclosed\n").unwrap();
+
+        let project = super::analyze_dir(&root).unwrap();
+
+        let members: Vec<&str> = project.members.iter().map(|m| m.name.as_str()).collect();
+
+        assert_eq!(project.name, "shop");
+        assert_eq!(members, ["ui"]);
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn it_should_pick_the_identifier_by_priority() {
+        let root = std::env::temp_dir().join("lp_walker_identifier_priority_test");
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(&root).unwrap();
+
+        std::fs::write(root.join("Cargo.toml"), "[package]\nname = \"app\"\n").unwrap();
+        std::fs::write(root.join("package.json"), "{}").unwrap();
+        std::fs::write(root.join("setup.py"), "").unwrap();
+
+        let project_type = |names: &[&str]| {
+            let files: Vec<PathBuf> = names.iter().map(|name| root.join(name)).collect();
+
+            get_relevant_project(&files, &root, std::time::SystemTime::now())
+                .unwrap()
+                .project_type
+        };
+
+        assert_eq!(
+            project_type(&["setup.py", "package.json", "Cargo.toml"]),
+            ProjectType::Rust
+        );
+        assert_eq!(
+            project_type(&["setup.py", "package.json"]),
+            ProjectType::Node
+        );
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
 }
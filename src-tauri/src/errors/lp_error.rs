@@ -1,4 +1,4 @@
-use std::{error::Error, fmt::Debug};
+use std::fmt::Debug;
 
 use serde::Serialize;
 use tauri::InvokeError;
@@ -12,6 +12,7 @@ pub enum LpError {
     IoError(std::io::Error),
     SerdeError(serde_json::Error),
     YamlError(serde_yaml::Error),
+    XmlError(roxmltree::Error),
 }
 
 impl std::fmt::Display for LpError {
@@ -19,22 +20,14 @@ impl std::fmt::Display for LpError {
         match self {
             Self::ConfigError(err) => write!(f, "{err}"),
             Self::Error(err) => write!(f, "{err}"),
-            Self::IoError(err) => {
-                let source = err.source();
-                let _ = write!(f, "{source:?}");
-                write!(f, "{err}")
-            }
+            Self::IoError(err) => write!(f, "{err}"),
             Self::SerdeError(err) => write!(f, "{err}"),
             Self::YamlError(err) => write!(f, "{err}"),
+            Self::XmlError(err) => write!(f, "{err}"),
         }
     }
 }
 
-#[derive(Serialize)]
-struct ErrorWrapper {
-    error: String,
-}
-
 impl From<InvokeError> for LpError {
     fn from(value: InvokeError) -> Self {
         let message = format!("{value:?}");
@@ -42,18 +35,19 @@ impl From<InvokeError> for LpError {
     }
 }
 
+impl From<tauri::api::Error> for LpError {
+    fn from(value: tauri::api::Error) -> Self {
+        LpError::Error(value.to_string())
+    }
+}
+
+/// Commands fail with the error's message, as shown to the user
 impl Serialize for LpError {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: serde::Serializer,
     {
-        let message = "{self:?}";
-
-        let wrapper = ErrorWrapper {
-            error: message.to_string(),
-        };
-
-        wrapper.serialize(serializer)
+        serializer.serialize_str(&self.to_string())
     }
 }
 
@@ -64,3 +58,25 @@ impl From<std::io::Error> for LpError {
         Self::IoError(value)
     }
 }
+
+#[cfg(test)]
+mod lp_error_tests {
+    use super::LpError;
+
+    #[test]
+    fn it_should_serialize_to_its_message() {
+        let err = LpError::Error(String::from("zed not found, is it installed?"));
+
+        assert_eq!(
+            serde_json::to_string(&err).unwrap(),
+            r#""zed not found, is it installed?""#
+        );
+
+        let err = LpError::from(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "no such file",
+        ));
+
+        assert_eq!(serde_json::to_value(&err).unwrap(), "no such file");
+    }
+}
@@ -2,12 +2,14 @@ use std::{
     collections::HashMap,
     path::PathBuf,
     sync::{Arc, Mutex},
-    time::SystemTime,
 };
 
 use serde::{Deserialize, Serialize};
 
-use crate::{config::Config, dir_walker, errors::lp_error::LpError, indexer::Index};
+use crate::{
+    config::Config, dir_walker, errors::lp_error::LpError, git::status::GitStatus, indexer::Index,
+    tasks::Task, timestamp::Timestamp,
+};
 
 #[derive(Deserialize, Serialize, Debug)]
 pub struct Cache {
@@ -32,11 +34,27 @@ pub struct Project {
     pub project_type: ProjectType,
 
     /// Last Modified type for project
-    pub last_modified: SystemTime,
+    pub last_modified: Timestamp,
 
     /// Documentantion file for the project
     /// Mostly Readme.md
     pub documentation_file: Option<String>,
+
+    /// Ecosystem specific details parsed from the project's manifest
+    #[serde(default)]
+    pub details: Option<ProjectDetails>,
+
+    /// Member packages, if the project is a Cargo or npm/yarn/pnpm workspace
+    #[serde(default)]
+    pub members: Vec<WorkspaceMember>,
+
+    /// Branch, changes, upstream and last commit, if the project is a git repo
+    #[serde(default)]
+    pub git_status: Option<GitStatus>,
+
+    /// Scripts, aliases and targets that can be run in the project
+    #[serde(default)]
+    pub tasks: Vec<Task>,
 }
 
 impl Project {
@@ -53,16 +71,117 @@ impl Project {
             description,
             language_map: HashMap::new(),
             project_type,
-            last_modified: SystemTime::now(),
+            last_modified: Timestamp::now(),
             documentation_file: None,
+            details: None,
+            members: Vec::new(),
+            git_status: None,
+            tasks: Vec::new(),
         }
     }
+
+    /// Returns the member at `path`, if any
+    pub fn member(&self, path: &str) -> Option<&WorkspaceMember> {
+        self.members.iter().find(|m| m.path == path)
+    }
+}
+
+/// A package inside a workspace project
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct WorkspaceMember {
+    pub name: String,
+    pub path: String,
+    pub description: Option<String>,
+    pub project_type: ProjectType,
+    pub documentation_file: Option<String>,
+}
+
+impl From<Project> for WorkspaceMember {
+    fn from(project: Project) -> Self {
+        WorkspaceMember {
+            name: project.name,
+            path: project.path,
+            description: project.description,
+            project_type: project.project_type,
+            documentation_file: project.documentation_file,
+        }
+    }
+}
+
+/// Extra details for a project, read from its manifest file(s)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum ProjectDetails {
+    Go(GoModule),
+    Python(PythonProject),
+    Jvm(JvmBuild),
+}
+
+/// Details of a Go module, parsed from `go.mod` and `go.work`
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct GoModule {
+    /// Module path, e.g. `github.com/org/service`
+    pub module: String,
+    /// Go version from the `go` directive
+    pub go_version: Option<String>,
+    /// Direct requirements, `// indirect` ones are skipped
+    pub requires: Vec<GoRequire>,
+    /// Directories listed in `go.work`, if the project is a workspace
+    pub workspace: Option<Vec<String>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct GoRequire {
+    pub path: String,
+    pub version: String,
+}
+
+/// Details of a Python project, from `pyproject.toml`, `setup.cfg`,
+/// `setup.py` or `requirements*.txt`, in that order of preference
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct PythonProject {
+    pub version: Option<String>,
+    /// `build-backend` of `[build-system]`, e.g. `poetry.core.masonry.api`
+    pub build_backend: Option<String>,
+    pub package_manager: Option<PythonPackageManager>,
+    /// Names of the declared dependencies, without version specifiers
+    pub dependencies: Vec<String>,
+}
+
+/// Details of a Maven or Gradle build
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct JvmBuild {
+    pub build_tool: JvmBuildTool,
+    pub group_id: Option<String>,
+    pub artifact_id: Option<String>,
+    pub version: Option<String>,
+    /// Sub modules of a multi-module build, as paths relative to the project
+    pub modules: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub enum JvmBuildTool {
+    #[default]
+    Maven,
+    Gradle,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum PythonPackageManager {
+    Pip,
+    Pipenv,
+    Poetry,
+    Hatch,
+    Pdm,
+    Uv,
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
 pub enum ProjectType {
     Rust,
+    Go,
     Python,
+    Java,
+    Kotlin,
     Flutter,
     Ruby,
     NextJs,
@@ -75,9 +194,11 @@ pub enum ProjectType {
 }
 
 /// Searches for projects under `project_dirs`
-pub fn initiate_search(config: &Config) -> Result<(), LpError> {
-    println!("Initating Search");
-    println!("For Projects {:?}", config.project_dirs);
+/// Projects that were deleted, moved or are no longer under
+/// any of `project_dirs` are pruned from the index and returned
+pub fn initiate_search(config: &Config) -> Result<Vec<Project>, LpError> {
+    eprintln!("Initating Search");
+    eprintln!("For Projects {:?}", config.project_dirs);
 
     let index_path = std::env::var("LP_CONFIG_PATH").unwrap_or(String::from("./"));
 
@@ -87,15 +208,38 @@ pub fn initiate_search(config: &Config) -> Result<(), LpError> {
 
     let index: Arc<Mutex<Index>> = Arc::new(Mutex::new(Index::load_or_default()));
 
-    dir_walker::walker::analyze_all_dirs(config, 4, 4, Arc::clone(&index));
+    let pruned = index_projects(config, &index);
 
     index.lock().unwrap().save()?;
 
     let index_len = index.lock().unwrap().projects().len();
 
-    println!("Index Saved -> Len, {}", index_len);
+    eprintln!("Index Saved -> Len, {}", index_len);
+
+    Ok(pruned)
+}
+
+/// Walks `project_dirs` into `index`, without saving it
+/// Returns the projects pruned from it, see `initiate_search`
+pub fn index_projects(config: &Config, index: &Arc<Mutex<Index>>) -> Vec<Project> {
+    dir_walker::walker::analyze_all_dirs(
+        config,
+        4,
+        dir_walker::walker::MAX_DEPTH,
+        Arc::clone(index),
+    );
+
+    let pruned = index.lock().unwrap().prune(&config.project_dirs);
+
+    for project in pruned.iter() {
+        eprintln!(
+            "Pruned {name} at {path}",
+            name = project.name,
+            path = project.path
+        );
+    }
 
-    Ok(())
+    pruned
 }
 
 #[cfg(test)]
@@ -0,0 +1,3 @@
+pub mod remote_url;
+pub mod status;
+pub mod unsaved;
@@ -0,0 +1,315 @@
+use std::path::Path;
+
+use git2::{BranchType, ErrorCode, Repository, Status, StatusOptions};
+use serde::{Deserialize, Serialize};
+
+/// Local state of a project's git repository
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct GitStatus {
+    pub head: GitHead,
+    /// Files with changes in the index
+    pub staged: usize,
+    /// Tracked files with changes in the working tree
+    pub unstaged: usize,
+    pub untracked: usize,
+    pub conflicted: usize,
+    /// Upstream of the current branch, e.g. `origin/main`
+    pub upstream: Option<String>,
+    /// Url of the remote the upstream is on
+    #[serde(default)]
+    pub upstream_url: Option<String>,
+    /// Name of the upstream on its remote, `fork/feature/x` -> `feature/x`
+    #[serde(default)]
+    pub upstream_branch: Option<String>,
+    /// Commits on the current branch not on its upstream
+    pub ahead: usize,
+    /// Commits on the upstream not on the current branch
+    pub behind: usize,
+    pub last_commit: Option<GitCommit>,
+    pub stash_count: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum GitHead {
+    /// On a branch
+    Branch(String),
+    /// Detached at a commit, holds the short hash
+    Detached(String),
+    /// On a branch without any commits yet
+    Unborn(String),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct GitCommit {
+    pub hash: String,
+    pub summary: Option<String>,
+    pub author: Option<String>,
+    /// Commit time, seconds since unix epoch
+    pub time: i64,
+}
+
+/// Get git status of the repository at `project_dir`,
+/// `None` if the project is not a git repo
+pub fn get_git_status(project_dir: &Path) -> Option<GitStatus> {
+    let mut repo = Repository::open(project_dir).ok()?;
+
+    repo_status(&mut repo)
+}
+
+/// Git status of an opened `repo`, `None` if its HEAD can't be read
+pub fn repo_status(repo: &mut Repository) -> Option<GitStatus> {
+    let head = get_head(repo)?;
+
+    let mut status = GitStatus {
+        head,
+        staged: 0,
+        unstaged: 0,
+        untracked: 0,
+        conflicted: 0,
+        upstream: None,
+        upstream_url: None,
+        upstream_branch: None,
+        ahead: 0,
+        behind: 0,
+        last_commit: None,
+        stash_count: 0,
+    };
+
+    count_changes(repo, &mut status);
+
+    if let GitHead::Branch(branch) = &status.head {
+        if let Some((upstream, ahead, behind)) = get_upstream(repo, branch) {
+            status.upstream = Some(upstream);
+            status.ahead = ahead;
+            status.behind = behind;
+        }
+
+        if let Some((url, upstream_branch)) = get_upstream_remote(repo, branch) {
+            status.upstream_url = Some(url);
+            status.upstream_branch = Some(upstream_branch);
+        }
+    }
+
+    status.last_commit = repo
+        .head()
+        .and_then(|head| head.peel_to_commit())
+        .ok()
+        .map(|commit| GitCommit {
+            hash: commit.id().to_string(),
+            summary: commit.summary().map(|s| s.to_string()),
+            author: commit.author().name().map(|n| n.to_string()),
+            time: commit.time().seconds(),
+        });
+
+    let _ = repo.stash_foreach(|_, _, _| {
+        status.stash_count += 1;
+        true
+    });
+
+    Some(status)
+}
+
+fn get_head(repo: &Repository) -> Option<GitHead> {
+    match repo.head() {
+        Ok(head) => {
+            if repo.head_detached().unwrap_or(false) {
+                let id = head.target()?.to_string();
+
+                Some(GitHead::Detached(id[..7.min(id.len())].to_string()))
+            } else {
+                Some(GitHead::Branch(head.shorthand()?.to_string()))
+            }
+        }
+        Err(err) if err.code() == ErrorCode::UnbornBranch => {
+            let head = repo.find_reference("HEAD").ok()?;
+
+            let target = head.symbolic_target()?;
+
+            Some(GitHead::Unborn(
+                target.trim_start_matches("refs/heads/").to_string(),
+            ))
+        }
+        Err(err) => {
+            eprintln!("ERROR: reading HEAD of {:?} : {err}", repo.path());
+            None
+        }
+    }
+}
+
+fn count_changes(repo: &Repository, status: &mut GitStatus) {
+    let mut options = StatusOptions::new();
+
+    options
+        .include_untracked(true)
+        .recurse_untracked_dirs(false)
+        .exclude_submodules(true);
+
+    let statuses = match repo.statuses(Some(&mut options)) {
+        Ok(statuses) => statuses,
+        Err(err) => {
+            eprintln!("ERROR: reading status of {:?} : {err}", repo.path());
+            return;
+        }
+    };
+
+    let staged = Status::INDEX_NEW
+        | Status::INDEX_MODIFIED
+        | Status::INDEX_DELETED
+        | Status::INDEX_RENAMED
+        | Status::INDEX_TYPECHANGE;
+
+    let unstaged =
+        Status::WT_MODIFIED | Status::WT_DELETED | Status::WT_RENAMED | Status::WT_TYPECHANGE;
+
+    for entry in statuses.iter() {
+        let s = entry.status();
+
+        if s.is_conflicted() {
+            status.conflicted += 1;
+            continue;
+        }
+
+        if s.intersects(staged) {
+            status.staged += 1;
+        }
+
+        if s.intersects(unstaged) {
+            status.unstaged += 1;
+        }
+
+        if s.is_wt_new() {
+            status.untracked += 1;
+        }
+    }
+}
+
+/// Upstream name of local `branch`, with ahead and behind counts
+fn get_upstream(repo: &Repository, branch: &str) -> Option<(String, usize, usize)> {
+    let local = repo.find_branch(branch, BranchType::Local).ok()?;
+
+    let upstream = local.upstream().ok()?;
+
+    let name = upstream.name().ok()??.to_string();
+
+    let local_oid = local.get().target()?;
+    let upstream_oid = upstream.get().target()?;
+
+    let (ahead, behind) = repo.graph_ahead_behind(local_oid, upstream_oid).ok()?;
+
+    Some((name, ahead, behind))
+}
+
+/// Url of the remote the upstream of local `branch` is on,
+/// with the upstream's name on that remote
+/// Remote names may hold `/`, so the name is not split from the upstream's
+fn get_upstream_remote(repo: &Repository, branch: &str) -> Option<(String, String)> {
+    let refname = format!("refs/heads/{branch}");
+
+    let remote_name = repo.branch_upstream_remote(&refname).ok()?;
+    let remote_name = remote_name.as_str()?;
+
+    let upstream_ref = repo.branch_upstream_name(&refname).ok()?;
+
+    let upstream_branch = upstream_ref
+        .as_str()?
+        .strip_prefix(&format!("refs/remotes/{remote_name}/"))?
+        .to_string();
+
+    let url = repo.find_remote(remote_name).ok()?.url()?.to_string();
+
+    Some((url, upstream_branch))
+}
+
+#[cfg(test)]
+mod status_tests {
+    use git2::{BranchType, Repository, Signature};
+
+    use super::{get_git_status, GitHead};
+
+    #[test]
+    fn it_should_count_changes_and_read_last_commit() {
+        let dir = std::env::temp_dir().join("lp_git_status_test");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let repo = Repository::init(&dir).unwrap();
+
+        let status = get_git_status(&dir).unwrap();
+
+        assert!(matches!(status.head, GitHead::Unborn(_)));
+        assert!(status.last_commit.is_none());
+
+        std::fs::write(dir.join("a.txt"), "a").unwrap();
+
+        let mut index = repo.index().unwrap();
+        index.add_path(std::path::Path::new("a.txt")).unwrap();
+        index.write().unwrap();
+
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let sig = Signature::now("Dev", "dev@example.com").unwrap();
+        repo.commit(Some("HEAD"), &sig, &sig, "Initial commit", &tree, &[])
+            .unwrap();
+
+        std::fs::write(dir.join("a.txt"), "changed").unwrap();
+        std::fs::write(dir.join("b.txt"), "b").unwrap();
+        std::fs::write(dir.join("c.txt"), "c").unwrap();
+        index.add_path(std::path::Path::new("c.txt")).unwrap();
+        index.write().unwrap();
+
+        let status = get_git_status(&dir).unwrap();
+
+        assert!(matches!(status.head, GitHead::Branch(_)));
+        assert_eq!(status.staged, 1);
+        assert_eq!(status.unstaged, 1);
+        assert_eq!(status.untracked, 1);
+        assert_eq!(status.upstream, None);
+        assert_eq!(status.stash_count, 0);
+
+        let commit = status.last_commit.unwrap();
+        assert_eq!(commit.summary.as_deref(), Some("Initial commit"));
+        assert_eq!(commit.author.as_deref(), Some("Dev"));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn it_should_resolve_the_remote_of_the_upstream() {
+        let dir = std::env::temp_dir().join("lp_git_upstream_test");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let repo = Repository::init(&dir).unwrap();
+
+        let tree = repo
+            .find_tree(repo.index().unwrap().write_tree().unwrap())
+            .unwrap();
+        let sig = Signature::now("Dev", "dev@example.com").unwrap();
+        let commit = repo
+            .commit(Some("HEAD"), &sig, &sig, "Initial commit", &tree, &[])
+            .unwrap();
+
+        repo.remote("me/fork", "git@github.com:me/repo.git")
+            .unwrap();
+        repo.reference("refs/remotes/me/fork/feature/x", commit, true, "")
+            .unwrap();
+
+        let head = repo.head().unwrap();
+        let branch = head.shorthand().unwrap().to_string();
+
+        repo.find_branch(&branch, BranchType::Local)
+            .unwrap()
+            .set_upstream(Some("me/fork/feature/x"))
+            .unwrap();
+
+        let status = get_git_status(&dir).unwrap();
+
+        assert_eq!(status.upstream.as_deref(), Some("me/fork/feature/x"));
+        assert_eq!(
+            status.upstream_url.as_deref(),
+            Some("git@github.com:me/repo.git")
+        );
+        assert_eq!(status.upstream_branch.as_deref(), Some("feature/x"));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
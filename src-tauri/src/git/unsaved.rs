@@ -0,0 +1,182 @@
+// Finds local work that exists nowhere else
+
+use std::path::Path;
+
+use git2::{BranchType, Repository};
+use serde::{Deserialize, Serialize};
+
+use crate::{file_handler::Project, git::status::repo_status};
+
+/// Work in a repository that is not on any remote
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct UnsavedWork {
+    pub name: String,
+    pub path: String,
+    /// Staged, unstaged, untracked and conflicted files
+    pub uncommitted: usize,
+    /// Local branches without an upstream
+    pub branches_without_upstream: Vec<String>,
+    /// Commits on local branches or HEAD not on any remote-tracking ref
+    pub unpushed_commits: usize,
+    pub stash_count: usize,
+    /// The repository has no remotes at all
+    pub no_remote: bool,
+}
+
+impl UnsavedWork {
+    /// Whether the repository holds any work that would be lost with it
+    pub fn is_risky(&self) -> bool {
+        self.uncommitted > 0
+            || !self.branches_without_upstream.is_empty()
+            || self.unpushed_commits > 0
+            || self.stash_count > 0
+            || self.no_remote
+    }
+}
+
+/// Unsaved work in the repository of `project`, which may be
+/// in a parent dir of the project, `None` if it is not in a git repo
+pub fn find_unsaved_work(project: &Project) -> Option<UnsavedWork> {
+    let mut repo = Repository::discover(Path::new(&project.path)).ok()?;
+
+    let status = repo_status(&mut repo)?;
+
+    let no_remote = repo.remotes().map(|r| r.is_empty()).unwrap_or(true);
+
+    let branches_without_upstream = match repo.branches(Some(BranchType::Local)) {
+        Ok(branches) => branches
+            .filter_map(|b| b.ok())
+            .filter(|(b, _)| b.upstream().is_err())
+            .filter_map(|(b, _)| b.name().ok().flatten().map(|n| n.to_string()))
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+
+    Some(UnsavedWork {
+        name: project.name.clone(),
+        path: project.path.clone(),
+        uncommitted: status.staged + status.unstaged + status.untracked + status.conflicted,
+        branches_without_upstream,
+        unpushed_commits: count_unpushed_commits(&repo).unwrap_or(0),
+        stash_count: status.stash_count,
+        no_remote,
+    })
+}
+
+/// Commits reachable from local branches or HEAD,
+/// but not from any `refs/remotes/*`
+fn count_unpushed_commits(repo: &Repository) -> Result<usize, git2::Error> {
+    let mut revwalk = repo.revwalk()?;
+
+    if let Ok(head) = repo.head() {
+        if let Some(oid) = head.target() {
+            revwalk.push(oid)?;
+        }
+    }
+
+    for reference in repo.references_glob("refs/heads/*")? {
+        if let Some(oid) = reference?.target() {
+            revwalk.push(oid)?;
+        }
+    }
+
+    for reference in repo.references_glob("refs/remotes/*")? {
+        if let Some(oid) = reference?.target() {
+            revwalk.hide(oid)?;
+        }
+    }
+
+    Ok(revwalk.filter(|oid| oid.is_ok()).count())
+}
+
+/// Risky repositories among `projects`, most uncommitted files first
+pub fn unsaved_work_report(projects: &[Project]) -> Vec<UnsavedWork> {
+    let mut report: Vec<UnsavedWork> = projects
+        .iter()
+        .filter_map(find_unsaved_work)
+        .filter(|w| w.is_risky())
+        .collect();
+
+    report.sort_by(|a, b| {
+        b.uncommitted
+            .cmp(&a.uncommitted)
+            .then(b.unpushed_commits.cmp(&a.unpushed_commits))
+    });
+
+    report
+}
+
+#[cfg(test)]
+mod unsaved_tests {
+    use git2::{Repository, Signature};
+
+    use super::find_unsaved_work;
+    use crate::file_handler::{Project, ProjectType};
+
+    #[test]
+    fn it_should_report_work_missing_from_remotes() {
+        let dir = std::env::temp_dir().join("lp_git_unsaved_test");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let repo = Repository::init(&dir).unwrap();
+
+        std::fs::write(dir.join("a.txt"), "a").unwrap();
+
+        let mut index = repo.index().unwrap();
+        index.add_path(std::path::Path::new("a.txt")).unwrap();
+        index.write().unwrap();
+
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let sig = Signature::now("Dev", "dev@example.com").unwrap();
+        repo.commit(Some("HEAD"), &sig, &sig, "Initial commit", &tree, &[])
+            .unwrap();
+
+        std::fs::write(dir.join("b.txt"), "b").unwrap();
+
+        let project = Project::base(
+            String::from("unsaved"),
+            dir.to_str().unwrap().to_string(),
+            None,
+            ProjectType::Rust,
+        );
+
+        let work = find_unsaved_work(&project).unwrap();
+
+        assert!(work.is_risky());
+        assert!(work.no_remote);
+        assert_eq!(work.uncommitted, 1);
+        assert_eq!(work.unpushed_commits, 1);
+        assert_eq!(work.branches_without_upstream.len(), 1);
+        assert_eq!(work.stash_count, 0);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn it_should_report_work_of_a_project_nested_in_a_repo() {
+        let dir = std::env::temp_dir().join("lp_git_unsaved_nested_test");
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let nested = dir.join("packages/app");
+        std::fs::create_dir_all(&nested).unwrap();
+
+        Repository::init(&dir).unwrap();
+
+        std::fs::write(nested.join("package.json"), "{}").unwrap();
+
+        let project = Project::base(
+            String::from("app"),
+            nested.to_str().unwrap().to_string(),
+            None,
+            ProjectType::Node,
+        );
+
+        let work = find_unsaved_work(&project).unwrap();
+
+        assert!(work.no_remote);
+        assert_eq!(work.uncommitted, 1);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
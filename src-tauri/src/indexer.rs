@@ -8,19 +8,21 @@ use std::{
 
 use serde::{Deserialize, Serialize};
 
-use crate::{config::get_config_dir, errors::lp_error::LpError, file_handler::Project};
+use crate::{
+    config::get_config_dir, errors::lp_error::LpError, file_handler::Project, timestamp::Timestamp,
+};
 
 #[derive(Serialize, Deserialize)]
 pub struct Index {
     projects: BTreeMap<PathBuf, Project>,
-    last_indexed: SystemTime,
+    last_indexed: Timestamp,
 }
 
 impl Default for Index {
     fn default() -> Self {
         Self {
             projects: BTreeMap::new(),
-            last_indexed: SystemTime::now(),
+            last_indexed: Timestamp::now(),
         }
     }
 }
@@ -41,7 +43,7 @@ impl Index {
             match std::fs::File::open(&config) {
                 Ok(file) => match serde_json::from_reader(file) {
                     Ok(json) => {
-                        println!("Index Loaded From File {config:?}");
+                        eprintln!("Index Loaded From File {config:?}");
                         return Ok(json);
                     }
                     Err(err) => {
@@ -70,7 +72,7 @@ impl Index {
             match std::fs::File::open(&home_path) {
                 Ok(file) => match serde_json::from_reader(file) {
                     Ok(json) => {
-                        println!("Index Loaded From File {home_path:?}");
+                        eprintln!("Index Loaded From File {home_path:?}");
                         return json;
                     }
                     Err(err) => {
@@ -104,17 +106,17 @@ impl Index {
             .truncate(true)
             .open(&home_path);
 
-        self.last_indexed = SystemTime::now();
+        self.last_indexed = Timestamp::now();
 
         match file {
             Ok(mut file) => {
                 serde_json::to_writer(&file, &self)?;
                 let canonical = home_path.canonicalize().unwrap();
-                println!("Index saved to {canonical:?}");
+                eprintln!("Index saved to {canonical:?}");
                 let _ = file.flush();
             }
             Err(err) => {
-                println!("ERROR: {err}");
+                eprintln!("ERROR: {err}");
                 return Err(LpError::IoError(err));
             }
         }
@@ -128,11 +130,33 @@ impl Index {
         self.projects.insert(path.to_path_buf(), project);
     }
 
+    /// Removes the project at `path`, returns it if it was indexed
+    pub fn remove_project(&mut self, path: &PathBuf) -> Option<Project> {
+        self.projects.remove(path)
+    }
+
+    /// Removes projects whose path no longer exists
+    /// or is not under any of the configured `roots`
+    /// Returns the removed projects
+    pub fn prune(&mut self, roots: &[PathBuf]) -> Vec<Project> {
+        let stale: Vec<PathBuf> = self
+            .projects
+            .keys()
+            .filter(|path| !path.exists() || !roots.iter().any(|root| path.starts_with(root)))
+            .cloned()
+            .collect();
+
+        stale
+            .iter()
+            .filter_map(|path| self.projects.remove(path))
+            .collect()
+    }
+
     /// Returns projects as `Vec<Project>`
     pub fn projects(&self) -> Vec<Project> {
         let mut projects: Vec<Project> = self.projects.iter().map(|(_, p)| p.clone()).collect();
 
-        projects.sort_unstable_by(|a, b| b.last_modified.partial_cmp(&a.last_modified).unwrap());
+        projects.sort_unstable_by_key(|p| std::cmp::Reverse(p.last_modified));
 
         projects
     }
@@ -142,24 +166,82 @@ impl Index {
         &self.projects
     }
 
+    /// Returns the project at `path`, or the workspace
+    /// project `path` is a member of
+    pub fn find_project(&self, path: &PathBuf) -> Option<&Project> {
+        if let Some(project) = self.projects.get(path) {
+            return Some(project);
+        }
+
+        let member_path = path.to_string_lossy();
+
+        self.projects
+            .values()
+            .find(|p| p.member(&member_path).is_some())
+    }
+
     /// A project requires reindexing
     /// * If it is not already present in the index
     /// * And the directory is modified after being indexed
     pub fn should_reindex(&self, path: &PathBuf, last_modified: SystemTime) -> bool {
         if let Some(project) = self.projects.get(path) {
-            return project.last_modified < last_modified;
+            return project.last_modified < Timestamp::from(last_modified);
         }
 
         true
     }
 
-    pub fn last_indexed(&self) -> SystemTime {
+    pub fn last_indexed(&self) -> Timestamp {
         self.last_indexed
     }
 }
 
 #[cfg(test)]
 mod indexer_tests {
+    use std::path::PathBuf;
+
+    use crate::file_handler::{Project, ProjectType};
+    use crate::timestamp::Timestamp;
+
+    #[test]
+    fn it_should_prune_missing_and_unrooted_projects() {
+        let root = std::env::temp_dir().join("lp_indexer_prune_test");
+        let _ = std::fs::remove_dir_all(&root);
+
+        let kept = root.join("kept");
+        let deleted = root.join("deleted");
+        let outside = std::env::temp_dir();
+
+        std::fs::create_dir_all(&kept).unwrap();
+
+        let mut index = super::Index::default();
+
+        for path in [&kept, &deleted, &outside] {
+            let project = Project::base(
+                String::from("p"),
+                path.to_str().unwrap().to_string(),
+                None,
+                ProjectType::Rust,
+            );
+
+            index.add_project(path, project);
+        }
+
+        let mut pruned: Vec<PathBuf> = index
+            .prune(&[root.clone()])
+            .iter()
+            .map(|p| PathBuf::from(&p.path))
+            .collect();
+
+        pruned.sort();
+
+        assert_eq!(pruned, vec![outside, deleted]);
+        assert_eq!(index.projects().len(), 1);
+        assert!(index.projects_map().contains_key(&kept));
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
     #[test]
     fn it_should_load_index() {
         let index = super::Index::load();
@@ -168,4 +250,43 @@ mod indexer_tests {
 
         println!("Index Len -> {}", index.unwrap().projects().len());
     }
+
+    #[test]
+    fn it_should_load_an_index_saved_with_system_times() {
+        // index.json as written before `Timestamp`, with no project details
+        let saved = r#"{
+            "projects": {
+                "/home/me/projects/lp": {
+                    "name": "lp",
+                    "path": "/home/me/projects/lp",
+                    "git": ["https://github.com/me/lp"],
+                    "description": null,
+                    "language_map": { "Rust": 0.9 },
+                    "project_type": "Rust",
+                    "last_modified": { "secs_since_epoch": 1700000000, "nanos_since_epoch": 999999 },
+                    "documentation_file": "/home/me/projects/lp/README.md"
+                }
+            },
+            "last_indexed": { "secs_since_epoch": 1700000100, "nanos_since_epoch": 0 }
+        }"#;
+
+        let index: super::Index = serde_json::from_str(saved).unwrap();
+
+        assert_eq!(
+            index.last_indexed(),
+            Timestamp::from_millis(1_700_000_100_000)
+        );
+
+        let project = index
+            .find_project(&PathBuf::from("/home/me/projects/lp"))
+            .unwrap();
+
+        assert_eq!(project.project_type, ProjectType::Rust);
+        assert_eq!(
+            project.last_modified,
+            Timestamp::from_millis(1_700_000_000_000)
+        );
+        assert!(project.members.is_empty());
+        assert!(project.tasks.is_empty());
+    }
 }
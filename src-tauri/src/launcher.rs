@@ -0,0 +1,671 @@
+// Editors, terminals and file managers projects are opened with,
+// configured in `lp.config.json`
+
+use std::{
+    collections::BTreeMap,
+    env,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    errors::lp_error::LpError,
+    file_handler::{Project, ProjectType},
+};
+
+/// Launcher used when neither the config nor the project type name one
+pub const DEFAULT_LAUNCHER: &str = "code";
+
+/// Launchers for project types, used if their command is installed
+const PROJECT_TYPE_LAUNCHERS: [(&str, &str); 6] = [
+    ("Flutter", "android-studio"),
+    ("Rust", "rustrover"),
+    ("Go", "goland"),
+    ("Python", "pycharm"),
+    ("Java", "idea"),
+    ("Kotlin", "idea"),
+];
+
+/// Directories searched after `PATH`, apps started from the dock or
+/// a desktop entry often get a `PATH` without them
+#[cfg(unix)]
+const FALLBACK_DIRS: [&str; 3] = ["/usr/local/bin", "/opt/homebrew/bin", "~/.local/bin"];
+
+#[cfg(not(unix))]
+const FALLBACK_DIRS: [&str; 0] = [];
+
+/// Startup command of a terminal, the shell stays open once it exits
+const KEEP_OPEN: &str = "{command}; exec \"${SHELL:-sh}\"";
+
+/// AppleScript opening a Terminal.app window in the path passed as its first
+/// argument, running the second one if any, the arguments need no quoting
+const MACOS_TERMINAL: &[&str] = &[
+    "-e",
+    "on run argv",
+    "-e",
+    "set cmd to \"cd \" & quoted form of item 1 of argv",
+    "-e",
+    "if (count of argv) > 1 then set cmd to cmd & \"; \" & item 2 of argv",
+    "-e",
+    "tell application \"Terminal\" to do script cmd",
+    "-e",
+    "tell application \"Terminal\" to activate",
+    "-e",
+    "end run",
+    "{path}",
+];
+
+/// `MACOS_TERMINAL` for iTerm
+const MACOS_ITERM: &[&str] = &[
+    "-e",
+    "on run argv",
+    "-e",
+    "set cmd to \"cd \" & quoted form of item 1 of argv",
+    "-e",
+    "if (count of argv) > 1 then set cmd to cmd & \"; \" & item 2 of argv",
+    "-e",
+    "tell application \"iTerm\"",
+    "-e",
+    "activate",
+    "-e",
+    "tell current session of (create window with default profile) to write text cmd",
+    "-e",
+    "end tell",
+    "-e",
+    "end run",
+    "{path}",
+];
+
+/// Built in terminals by name, with their command, the arguments opening
+/// them at `{path}` and the ones running `{command}` in them
+const BUILTIN_TERMINALS: [(&str, &str, &[&str], &[&str]); 10] = [
+    (
+        "gnome-terminal",
+        "gnome-terminal",
+        &["--working-directory={path}"],
+        &["--", "sh", "-c", KEEP_OPEN],
+    ),
+    (
+        "konsole",
+        "konsole",
+        &["--workdir", "{path}"],
+        &["-e", "sh", "-c", KEEP_OPEN],
+    ),
+    (
+        "kitty",
+        "kitty",
+        &["--directory", "{path}"],
+        &["sh", "-c", KEEP_OPEN],
+    ),
+    (
+        "alacritty",
+        "alacritty",
+        &["--working-directory", "{path}"],
+        &["-e", "sh", "-c", KEEP_OPEN],
+    ),
+    (
+        "wezterm",
+        "wezterm",
+        &["start", "--cwd", "{path}"],
+        &["--", "sh", "-c", KEEP_OPEN],
+    ),
+    ("xterm", "xterm", &[], &["-e", "sh", "-c", KEEP_OPEN]),
+    ("terminal", "osascript", MACOS_TERMINAL, &["{command}"]),
+    ("iterm", "osascript", MACOS_ITERM, &["{command}"]),
+    ("wt", "wt", &["-d", "{path}"], &["cmd", "/k", "{command}"]),
+    ("cmd", "cmd", &["/c", "start", "cmd"], &["/k", "{command}"]),
+];
+
+/// Terminals tried in order when none is configured
+#[cfg(target_os = "macos")]
+const DETECTED_TERMINALS: [&str; 1] = ["terminal"];
+
+#[cfg(windows)]
+const DETECTED_TERMINALS: [&str; 2] = ["wt", "cmd"];
+
+#[cfg(not(any(target_os = "macos", windows)))]
+const DETECTED_TERMINALS: [&str; 6] = [
+    "gnome-terminal",
+    "konsole",
+    "kitty",
+    "alacritty",
+    "wezterm",
+    "xterm",
+];
+
+/// A program projects are opened with
+/// `{path}` and `{name}` in `args` and `working_dir` are
+/// replaced by the project's
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Launcher {
+    /// Program to run, looked up in `PATH` unless it has a directory
+    pub command: String,
+
+    #[serde(default = "default_args")]
+    pub args: Vec<String>,
+
+    /// Directory the program runs in, the project's by default
+    #[serde(default)]
+    pub working_dir: Option<String>,
+}
+
+fn default_args() -> Vec<String> {
+    vec![String::from("{path}")]
+}
+
+/// Launchers by name and which to use for each project type,
+/// both on top of the built in ones
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct LauncherConfig {
+    /// Launcher for projects whose type has none, `code` if not set
+    pub default: Option<String>,
+
+    /// Launcher name per project type, e.g. `{ "Rust": "zed" }`
+    pub project_types: BTreeMap<String, String>,
+
+    /// Launchers added, or replacing built in ones of the same name
+    pub launchers: BTreeMap<String, Launcher>,
+
+    /// Terminal projects are opened in, `$TERMINAL` or the first
+    /// installed one of the platform if not set
+    pub terminal: Option<String>,
+
+    /// Terminals added, or replacing built in ones of the same name
+    pub terminals: BTreeMap<String, Terminal>,
+}
+
+/// A terminal emulator, `exec_args` are appended to `args` to run a
+/// startup command, `{command}` in them is replaced by it
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Terminal {
+    #[serde(flatten)]
+    pub launcher: Launcher,
+
+    /// e.g. `["-e", "sh", "-c", "{command}"]`, startup commands
+    /// are refused without them
+    #[serde(default)]
+    pub exec_args: Vec<String>,
+}
+
+/// What a launcher opens, a project or a member of a workspace
+#[derive(Debug, Clone)]
+pub struct Target<'a> {
+    pub name: &'a str,
+    pub path: &'a str,
+    pub project_type: Option<&'a ProjectType>,
+}
+
+impl<'a> Target<'a> {
+    /// The member of `project` at `path`, or `project` itself
+    pub fn of(project: &'a Project, path: &str) -> Self {
+        match project.member(path) {
+            Some(member) => Target {
+                name: &member.name,
+                path: &member.path,
+                project_type: Some(&member.project_type),
+            },
+            None => Target::from(project),
+        }
+    }
+}
+
+impl<'a> From<&'a Project> for Target<'a> {
+    fn from(project: &'a Project) -> Self {
+        Target {
+            name: &project.name,
+            path: &project.path,
+            project_type: Some(&project.project_type),
+        }
+    }
+}
+
+/// Built in launchers by name, and the command they run with the project path
+const BUILTIN_LAUNCHERS: [(&str, &str); 14] = [
+    ("android-studio", "studio"),
+    ("clion", "clion"),
+    ("code", "code"),
+    ("codium", "codium"),
+    ("cursor", "cursor"),
+    ("emacs", "emacs"),
+    ("fleet", "fleet"),
+    ("goland", "goland"),
+    ("idea", "idea"),
+    ("pycharm", "pycharm"),
+    ("rustrover", "rustrover"),
+    ("subl", "subl"),
+    ("webstorm", "webstorm"),
+    ("zed", "zed"),
+];
+
+fn builtin(name: &str) -> Option<Launcher> {
+    let (_, command) = BUILTIN_LAUNCHERS.iter().find(|(n, _)| *n == name)?;
+
+    Some(Launcher {
+        command: command.to_string(),
+        args: default_args(),
+        working_dir: None,
+    })
+}
+
+fn builtin_terminal(name: &str) -> Option<Terminal> {
+    let (_, command, args, exec_args) = BUILTIN_TERMINALS.iter().find(|(n, ..)| *n == name)?;
+
+    Some(Terminal {
+        launcher: Launcher {
+            command: command.to_string(),
+            args: args.iter().map(|a| a.to_string()).collect(),
+            working_dir: None,
+        },
+        exec_args: exec_args.iter().map(|a| a.to_string()).collect(),
+    })
+}
+
+impl LauncherConfig {
+    /// The launcher named `name`, configured or built in
+    pub fn launcher(&self, name: &str) -> Option<Launcher> {
+        self.launchers.get(name).cloned().or_else(|| builtin(name))
+    }
+
+    /// Names of all launchers, sorted
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = BUILTIN_LAUNCHERS
+            .iter()
+            .map(|(name, _)| name.to_string())
+            .collect();
+
+        names.extend(self.launchers.keys().cloned());
+        names.sort();
+        names.dedup();
+
+        names
+    }
+
+    /// Launcher `name`, or else the one configured for `project_type`,
+    /// the built in one for it if installed, or the default
+    pub fn resolve(
+        &self,
+        project_type: Option<&ProjectType>,
+        name: Option<&str>,
+    ) -> Result<(String, Launcher), LpError> {
+        let type_name = project_type.map(|t| format!("{t:?}"));
+
+        let configured = type_name
+            .as_ref()
+            .and_then(|t| self.project_types.get(t))
+            .map(String::as_str);
+
+        let installed = || {
+            let (_, name) = PROJECT_TYPE_LAUNCHERS
+                .iter()
+                .find(|(t, _)| Some(*t) == type_name.as_deref())?;
+
+            self.launcher(name)
+                .filter(|l| find_program(&l.command).is_some())
+                .map(|_| *name)
+        };
+
+        let name = name
+            .or(configured)
+            .or_else(installed)
+            .or(self.default.as_deref())
+            .unwrap_or(DEFAULT_LAUNCHER);
+
+        match self.launcher(name) {
+            Some(launcher) => Ok((name.to_string(), launcher)),
+            None => Err(LpError::Error(format!(
+                "Unknown launcher {name}, expected one of {}",
+                self.names().join(", ")
+            ))),
+        }
+    }
+
+    /// The terminal named `name`, configured or built in
+    pub fn terminal(&self, name: &str) -> Option<Terminal> {
+        self.terminals
+            .get(name)
+            .cloned()
+            .or_else(|| builtin_terminal(name))
+    }
+
+    /// The configured terminal, or else `$TERMINAL`,
+    /// or the first installed one of the platform
+    pub fn resolve_terminal(&self) -> Result<(String, Terminal), LpError> {
+        if let Some(name) = &self.terminal {
+            return match self.terminal(name) {
+                Some(terminal) => Ok((name.clone(), terminal)),
+                None => Err(LpError::Error(format!("Unknown terminal {name}"))),
+            };
+        }
+
+        let installed = |terminal: &Terminal| find_program(&terminal.launcher.command).is_some();
+
+        if let Some(name) = env::var("TERMINAL").ok().filter(|n| !n.is_empty()) {
+            // any other terminal is started in the project dir and run with `-e`
+            let terminal = self.terminal(&name).unwrap_or(Terminal {
+                launcher: Launcher {
+                    command: name.clone(),
+                    args: Vec::new(),
+                    working_dir: None,
+                },
+                exec_args: ["-e", "sh", "-c", KEEP_OPEN].map(String::from).to_vec(),
+            });
+
+            if installed(&terminal) {
+                return Ok((name, terminal));
+            }
+        }
+
+        DETECTED_TERMINALS
+            .iter()
+            .filter_map(|name| Some((name.to_string(), self.terminal(name)?)))
+            .find(|(_, terminal)| installed(terminal))
+            .ok_or(LpError::Error(format!(
+                "No terminal found, tried {}, set `terminal` in lp.config.json",
+                DETECTED_TERMINALS.join(", ")
+            )))
+    }
+}
+
+impl Terminal {
+    /// Command opening the terminal at `target`, running `startup` in it
+    pub fn command(&self, target: &Target, startup: Option<&str>) -> Result<Command, LpError> {
+        let mut command = self.launcher.command(target)?;
+
+        if let Some(startup) = startup {
+            if self.exec_args.is_empty() {
+                return Err(LpError::Error(format!(
+                    "{} cannot run a startup command, add `exec_args` for it to lp.config.json",
+                    self.launcher.command
+                )));
+            }
+
+            command.args(
+                self.exec_args
+                    .iter()
+                    .map(|arg| fill(arg, target).replace("{command}", startup)),
+            );
+        }
+
+        Ok(command)
+    }
+}
+
+/// `value` with `{path}` and `{name}` replaced by the ones of `target`
+fn fill(value: &str, target: &Target) -> String {
+    value
+        .replace("{path}", target.path)
+        .replace("{name}", target.name)
+}
+
+impl Launcher {
+    /// Command opening `target`, the program is looked up but not run
+    pub fn command(&self, target: &Target) -> Result<Command, LpError> {
+        let program = find_program(&self.command).ok_or_else(|| {
+            LpError::Error(format!(
+                "{} not found, install it or change it in lp.config.json",
+                self.command
+            ))
+        })?;
+
+        let working_dir = match &self.working_dir {
+            Some(dir) => fill(dir, target),
+            None => target.path.to_string(),
+        };
+
+        let mut command = Command::new(program);
+
+        command
+            .args(self.args.iter().map(|arg| fill(arg, target)))
+            .current_dir(working_dir)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+
+        Ok(command)
+    }
+
+    /// Starts the program for `target` without waiting for it to exit
+    pub fn spawn(&self, target: &Target) -> Result<(), LpError> {
+        start(self.command(target)?)
+    }
+}
+
+/// Runs `command` without waiting for it to exit
+fn start(mut command: Command) -> Result<(), LpError> {
+    let mut child = command.spawn().map_err(|err| {
+        LpError::Error(format!(
+            "Cannot run {}: {err}",
+            command.get_program().to_string_lossy()
+        ))
+    })?;
+
+    // reaped in the background, so no zombies pile up while the app runs
+    std::thread::spawn(move || child.wait());
+
+    Ok(())
+}
+
+/// Opens `target` with launcher `name`, or the one for its project type
+pub fn open(config: &LauncherConfig, target: &Target, name: Option<&str>) -> Result<(), LpError> {
+    let (name, launcher) = config.resolve(target.project_type, name)?;
+
+    eprintln!("Opening {} with {name}", target.path);
+
+    launcher.spawn(target)
+}
+
+/// Opens the terminal at `target`, running `startup` in it if given
+pub fn open_terminal(
+    config: &LauncherConfig,
+    target: &Target,
+    startup: Option<&str>,
+) -> Result<(), LpError> {
+    let (name, terminal) = config.resolve_terminal()?;
+
+    eprintln!("Opening {} in {name}", target.path);
+
+    start(terminal.command(target, startup)?)
+}
+
+/// Opens `target` in the file manager of the system
+pub fn open_file_manager(target: &Target) -> Result<(), LpError> {
+    let command = if cfg!(target_os = "macos") {
+        "open"
+    } else if cfg!(windows) {
+        "explorer"
+    } else {
+        "xdg-open"
+    };
+
+    Launcher {
+        command: String::from(command),
+        args: default_args(),
+        working_dir: None,
+    }
+    .spawn(target)
+}
+
+/// `command` as found in `PATH` or the fallback dirs, trying the
+/// `PATHEXT` extensions on Windows, commands with a directory are kept
+pub(crate) fn find_program(command: &str) -> Option<PathBuf> {
+    let path = Path::new(command);
+
+    if path.components().count() > 1 {
+        return path.is_file().then(|| path.to_path_buf());
+    }
+
+    let mut extensions = vec![String::new()];
+
+    if cfg!(windows) {
+        let pathext = env::var("PATHEXT").unwrap_or(String::from(".COM;.EXE;.BAT;.CMD"));
+
+        extensions.extend(
+            pathext
+                .split(';')
+                .filter(|e| !e.is_empty())
+                .map(String::from),
+        );
+    }
+
+    let home = dirs::home_dir().unwrap_or_default();
+
+    let fallback = FALLBACK_DIRS
+        .iter()
+        .map(|dir| match dir.strip_prefix("~/") {
+            Some(rest) => home.join(rest),
+            None => PathBuf::from(dir),
+        });
+
+    env::var_os("PATH")
+        .map(|paths| env::split_paths(&paths).collect::<Vec<_>>())
+        .unwrap_or_default()
+        .into_iter()
+        .chain(fallback)
+        .flat_map(|dir| {
+            extensions
+                .iter()
+                .map(move |ext| dir.join(format!("{command}{ext}")))
+        })
+        .find(|candidate| candidate.is_file())
+}
+
+#[cfg(test)]
+mod launcher_tests {
+    use std::path::Path;
+
+    use super::{builtin_terminal, Launcher, LauncherConfig, Target, Terminal, BUILTIN_TERMINALS};
+    use crate::file_handler::ProjectType;
+
+    #[test]
+    fn it_should_resolve_launchers_and_fill_in_arguments() {
+        let exe = std::env::current_exe().unwrap();
+        let dir = std::env::temp_dir();
+
+        let mut config = LauncherConfig::default();
+
+        config.launchers.insert(
+            String::from("this"),
+            Launcher {
+                command: exe.to_string_lossy().to_string(),
+                args: vec![String::from("--open"), String::from("{name}={path}")],
+                working_dir: Some(String::from("{path}")),
+            },
+        );
+        config
+            .project_types
+            .insert(String::from("Rust"), String::from("this"));
+
+        let (name, launcher) = config.resolve(Some(&ProjectType::Rust), None).unwrap();
+
+        assert_eq!(name, "this");
+
+        let target = Target {
+            name: "lp",
+            path: dir.to_str().unwrap(),
+            project_type: Some(&ProjectType::Rust),
+        };
+
+        let command = launcher.command(&target).unwrap();
+        let args: Vec<_> = command.get_args().map(|a| a.to_string_lossy()).collect();
+
+        assert_eq!(command.get_program(), exe.as_os_str());
+        assert_eq!(
+            args,
+            [String::from("--open"), format!("lp={}", dir.display())]
+        );
+        assert_eq!(command.get_current_dir(), Some(Path::new(&dir)));
+
+        let (name, _) = config.resolve(None, None).unwrap();
+
+        assert_eq!(name, "code");
+        assert_eq!(config.resolve(None, Some("zed")).unwrap().0, "zed");
+        assert!(config.resolve(None, Some("notepad")).is_err());
+
+        let missing = Launcher {
+            command: String::from("lp-missing-launcher"),
+            args: Vec::new(),
+            working_dir: None,
+        };
+
+        assert!(missing.command(&target).is_err());
+    }
+
+    #[test]
+    fn it_should_open_terminals_with_startup_commands() {
+        let exe = std::env::current_exe().unwrap();
+        let dir = std::env::temp_dir();
+
+        let terminal: Terminal = serde_json::from_value(serde_json::json!({
+            "command": exe,
+            "args": ["--cwd", "{path}"],
+            "exec_args": ["-e", "{command}"]
+        }))
+        .unwrap();
+
+        let mut config = LauncherConfig {
+            terminal: Some(String::from("this")),
+            ..LauncherConfig::default()
+        };
+
+        config.terminals.insert(String::from("this"), terminal);
+
+        let (name, terminal) = config.resolve_terminal().unwrap();
+
+        assert_eq!(name, "this");
+
+        let target = Target {
+            name: "lp",
+            path: dir.to_str().unwrap(),
+            project_type: None,
+        };
+
+        let args = |startup| {
+            let command = terminal.command(&target, startup).unwrap();
+
+            command
+                .get_args()
+                .map(|a| a.to_string_lossy().to_string())
+                .collect::<Vec<_>>()
+        };
+
+        let cwd = dir.to_string_lossy().to_string();
+
+        assert_eq!(args(None), ["--cwd", &cwd]);
+        assert_eq!(
+            args(Some("cargo test")),
+            ["--cwd", &cwd, "-e", "cargo test"]
+        );
+
+        let without_exec = Terminal {
+            exec_args: Vec::new(),
+            ..terminal.clone()
+        };
+
+        assert!(without_exec.command(&target, None).is_ok());
+        assert!(without_exec.command(&target, Some("ls")).is_err());
+
+        config.terminal = Some(String::from("teletype"));
+
+        assert!(config.resolve_terminal().is_err());
+
+        for (name, ..) in BUILTIN_TERMINALS {
+            let terminal = builtin_terminal(name).unwrap();
+
+            assert!(
+                terminal.exec_args.iter().any(|a| a.contains("{command}")),
+                "{name} cannot run a startup command"
+            );
+        }
+
+        // path and command are passed to the AppleScript as they are
+        for name in ["terminal", "iterm"] {
+            let terminal = builtin_terminal(name).unwrap();
+
+            assert_eq!(terminal.launcher.args.last().unwrap(), "{path}");
+            assert_eq!(terminal.exec_args, ["{command}"]);
+        }
+    }
+}
@@ -1,12 +1,79 @@
+use std::{collections::VecDeque, ops::Range};
+
+use rust_stemmers::{Algorithm, Stemmer};
+use serde::{Deserialize, Serialize};
+
+/// Common english words that carry no meaning for search
+const STOP_WORDS: [&str; 48] = [
+    "a", "about", "after", "all", "also", "an", "and", "any", "are", "as", "at", "be", "been",
+    "but", "by", "can", "for", "from", "has", "have", "how", "if", "in", "into", "is", "it", "its",
+    "may", "more", "not", "of", "on", "or", "so", "such", "than", "that", "the", "their", "then",
+    "there", "these", "this", "to", "was", "which", "will", "with",
+];
+
+/// Steps of the token pipeline
+/// Documents and queries must be lexed with the same config
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct LexerConfig {
+    /// Split `camelCase` and `PascalCase` words into their parts,
+    /// `snake_case` and `kebab-case` are split on punctuation anyway
+    pub split_identifiers: bool,
+    /// Skip punctuation instead of emitting each character as a token
+    pub drop_punctuation: bool,
+    /// Skip common english words, see `STOP_WORDS`
+    pub stop_words: bool,
+    /// Words skipped in addition to `STOP_WORDS`
+    pub extra_stop_words: Vec<String>,
+    /// Reduce words to their english stem, `parsing` -> `pars`
+    pub stemming: bool,
+}
+
+static DEFAULT_CONFIG: LexerConfig = LexerConfig {
+    split_identifiers: true,
+    drop_punctuation: true,
+    stop_words: true,
+    extra_stop_words: Vec::new(),
+    stemming: true,
+};
+
+impl Default for LexerConfig {
+    fn default() -> Self {
+        DEFAULT_CONFIG.clone()
+    }
+}
+
 // Lexer should contain the parsed document, doesn't modify
-#[derive(Debug)]
 pub struct Lexer<'a> {
     content: &'a [char],
+    /// Length of the whole content, to locate tokens
+    len: usize,
+    config: &'a LexerConfig,
+    stemmer: Option<Stemmer>,
+    /// Parts of a split identifier, not emitted yet
+    pending: VecDeque<(String, Range<usize>)>,
+    /// Last emitted token before stemming
+    surface: String,
+    /// Chars of the content the last emitted token came from
+    span: Range<usize>,
 }
 
 impl<'a> Lexer<'a> {
+    /// Lexer with the default pipeline
     pub fn new(content: &'a [char]) -> Self {
-        Self { content }
+        Self::with_config(content, &DEFAULT_CONFIG)
+    }
+
+    pub fn with_config(content: &'a [char], config: &'a LexerConfig) -> Self {
+        Self {
+            content,
+            len: content.len(),
+            config,
+            stemmer: config.stemming.then(|| Stemmer::create(Algorithm::English)),
+            pending: VecDeque::new(),
+            surface: String::new(),
+            span: 0..0,
+        }
     }
 
     fn chop(&mut self, n: usize) -> &'a [char] {
@@ -29,6 +96,24 @@ impl<'a> Lexer<'a> {
     }
 
     fn next_token(&mut self) -> Option<String> {
+        loop {
+            let (token, span) = match self.pending.pop_front() {
+                Some(token) => token,
+                None => self.next_raw_token()?,
+            };
+
+            if let Some(normalized) = self.normalize(&token) {
+                self.surface = token;
+                self.span = span;
+
+                return Some(normalized);
+            }
+        }
+    }
+
+    /// Next word, number or punctuation character, lowercased,
+    /// with the chars it spans
+    fn next_raw_token(&mut self) -> Option<(String, Range<usize>)> {
         // trim whitespaces from left
         self.trim_left();
 
@@ -36,28 +121,74 @@ impl<'a> Lexer<'a> {
             return None;
         }
 
+        let start = self.len - self.content.len();
+
         // Lex alphabetic words
         if self.content[0].is_alphabetic() {
-            let term = self
-                .chop_while(|x| x.is_alphabetic())
-                .iter()
-                .map(|x| x.to_ascii_lowercase())
-                .collect::<String>();
+            let word = self.chop_while(|x| x.is_alphabetic());
+
+            if !self.config.split_identifiers {
+                return Some((lowercase(word), start..start + word.len()));
+            }
 
-            // stemming of the term directly in lexer itself
+            self.pending
+                .extend(split_identifier(word).into_iter().map(|part| {
+                    (
+                        lowercase(&word[part.clone()]),
+                        start + part.start..start + part.end,
+                    )
+                }));
 
-            return Some(term);
+            return self.pending.pop_front();
         }
 
         //lex numbers
         if self.content[0].is_numeric() {
-            return Some(self.chop_while(|x| x.is_numeric()).iter().collect());
+            let number = self.chop_while(|x| x.is_numeric());
+
+            return Some((number.iter().collect(), start..start + number.len()));
         }
 
         // Unhandled tokens
         // proceed to next token for next iteration
         //
-        Some(self.chop(1).iter().collect())
+        Some((self.chop(1).iter().collect(), start..start + 1))
+    }
+
+    /// Applies the rest of the pipeline to a raw token,
+    /// `None` if the token is dropped
+    fn normalize(&self, token: &str) -> Option<String> {
+        if !token.starts_with(|c: char| c.is_alphanumeric()) {
+            return (!self.config.drop_punctuation).then(|| token.to_string());
+        }
+
+        if self.is_stop_word(token) {
+            return None;
+        }
+
+        match &self.stemmer {
+            Some(stemmer) => Some(stem(stemmer, token)),
+            None => Some(token.to_string()),
+        }
+    }
+
+    /// The last token returned by `next`, lowercased but not stemmed
+    pub fn surface(&self) -> &str {
+        &self.surface
+    }
+
+    /// Chars of the content the last token returned by `next` came from
+    pub fn span(&self) -> Range<usize> {
+        self.span.clone()
+    }
+
+    fn is_stop_word(&self, token: &str) -> bool {
+        (self.config.stop_words && STOP_WORDS.contains(&token))
+            || self
+                .config
+                .extra_stop_words
+                .iter()
+                .any(|w| w.eq_ignore_ascii_case(token))
     }
 
     fn trim_left(&mut self) {
@@ -67,6 +198,39 @@ impl<'a> Lexer<'a> {
     }
 }
 
+/// Snowball english stem of `word`
+fn stem(stemmer: &Stemmer, word: &str) -> String {
+    stemmer.stem(word).into_owned()
+}
+
+fn lowercase(word: &[char]) -> String {
+    word.iter().map(|x| x.to_ascii_lowercase()).collect()
+}
+
+/// Splits an alphabetic run at case changes, returns the parts' ranges
+/// `HttpClient` -> `Http`, `Client` and `HTTPServer` -> `HTTP`, `Server`
+fn split_identifier(word: &[char]) -> Vec<Range<usize>> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+
+    for i in 1..word.len() {
+        let lower_to_upper = word[i - 1].is_lowercase() && word[i].is_uppercase();
+
+        let acronym_end = word[i - 1].is_uppercase()
+            && word[i].is_uppercase()
+            && word.get(i + 1).is_some_and(|c| c.is_lowercase());
+
+        if lower_to_upper || acronym_end {
+            parts.push(start..i);
+            start = i;
+        }
+    }
+
+    parts.push(start..word.len());
+
+    parts
+}
+
 /// Iterator for the Lexer to iterate over
 /// generated token
 impl<'a> Iterator for Lexer<'a> {
@@ -76,3 +240,48 @@ impl<'a> Iterator for Lexer<'a> {
         self.next_token()
     }
 }
+
+#[cfg(test)]
+mod lexer_tests {
+    use super::{Lexer, LexerConfig};
+
+    fn lex(text: &str, config: &LexerConfig) -> Vec<String> {
+        let content: Vec<char> = text.chars().collect();
+
+        Lexer::with_config(&content, config).collect()
+    }
+
+    #[test]
+    fn it_should_split_identifiers_stem_and_drop_noise() {
+        let config = LexerConfig::default();
+
+        assert_eq!(
+            lex("markdown-parser-rust", &config),
+            ["markdown", "parser", "rust"]
+        );
+        assert_eq!(lex("parsing parses", &config), ["pars", "pars"]);
+        assert_eq!(lex("HttpClient", &config), lex("http client", &config));
+        assert_eq!(lex("HTTPServer", &config), ["http", "server"]);
+        assert_eq!(lex("users folder", &config), ["user", "folder"]);
+        assert_eq!(lex("snake_case v2", &config), ["snake", "case", "v", "2"]);
+        assert_eq!(lex("the art of the parsers", &config), ["art", "parser"]);
+
+        let raw = LexerConfig {
+            split_identifiers: false,
+            drop_punctuation: false,
+            stop_words: false,
+            extra_stop_words: Vec::new(),
+            stemming: false,
+        };
+
+        assert_eq!(lex("the HttpClient!", &raw), ["the", "httpclient", "!"]);
+
+        let content: Vec<char> = "a HttpClient".chars().collect();
+        let mut lexer = Lexer::new(&content);
+
+        lexer.next();
+        assert_eq!(lexer.span(), 2..6);
+        lexer.next();
+        assert_eq!(lexer.span(), 6..12);
+    }
+}
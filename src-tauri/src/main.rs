@@ -2,23 +2,32 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 use std::{
-    env,
-    process::Command,
+    path::{Path, PathBuf},
     sync::{Arc, Mutex},
 };
 
 use config::Config;
 use errors::lp_error::LpError;
 use file_handler::Project;
-use search::create_search_index;
+use tauri::Manager;
+use watcher::ProjectWatcher;
+pub mod cli;
 pub mod config;
 pub mod dir_walker;
 pub mod errors;
 pub mod file_handler;
+pub mod git;
 pub mod indexer;
+pub mod launcher;
 pub mod lexer;
+pub mod picker;
+pub mod query;
 pub mod search;
 pub mod search_model;
+pub mod snippet;
+pub mod tasks;
+pub mod timestamp;
+pub mod watcher;
 // Learn more about Tauri commands at https://tauri.app/v1/guides/features/command
 #[tauri::command]
 fn greet(name: &str) -> String {
@@ -31,31 +40,55 @@ pub struct AppState {
     index: Arc<Mutex<indexer::Index>>,
     search_model: Arc<Mutex<search_model::Model>>,
     is_indexing: Arc<Mutex<bool>>,
+    watcher: Arc<Mutex<Option<ProjectWatcher>>>,
+    tasks: tasks::runner::TaskRunner,
 }
 
+/// Web links for each remote of the project at `path`
 #[tauri::command]
-fn open_repo_url(path: String) -> Result<(), ()> {
-    let cur_dir = env::current_dir().unwrap();
-    let cur_dir = cur_dir.to_string_lossy();
-    println!("{cur_dir:?}");
-
-    let out = std::process::Command::new("sh")
-        .current_dir(path)
-        .arg(format!("{cur_dir}/git_open.sh"))
-        .output();
-
-    match out {
-        Ok(output) => {
-            println!("output");
-            println!("{:?}", output);
-        }
-        Err(e) => {
-            println!("{e}");
-            return Err(());
-        }
-    }
+fn get_repo_links(
+    path: String,
+    state: tauri::State<AppState>,
+) -> Result<Vec<git::remote_url::RepoLinks>, LpError> {
+    let index = state.index.lock().unwrap();
 
-    Ok(())
+    let project = index
+        .find_project(&PathBuf::from(&path))
+        .ok_or(LpError::Error(format!("Project not found at {path}")))?;
+
+    let status = project.git_status.as_ref();
+
+    // only a branch with an upstream exists on the remote,
+    // and only on the remote it tracks
+    let upstream =
+        status.and_then(|s| Some((s.upstream_url.as_deref()?, s.upstream_branch.as_deref()?)));
+
+    let commit = status
+        .and_then(|s| s.last_commit.as_ref())
+        .map(|c| c.hash.as_str());
+
+    Ok(git::remote_url::repo_links(&project.git, upstream, commit))
+}
+
+/// Opens the project at `path` in the browser, at the current branch
+/// on the remote it tracks, or else the first remote
+#[tauri::command]
+fn open_repo_url(
+    path: String,
+    app: tauri::AppHandle,
+    state: tauri::State<AppState>,
+) -> Result<(), LpError> {
+    let links = get_repo_links(path.clone(), state)?;
+
+    let link = links
+        .iter()
+        .find(|link| link.branch.is_some())
+        .or(links.first())
+        .ok_or(LpError::Error(format!("No web url for remotes of {path}")))?;
+
+    let url = link.branch.clone().unwrap_or(link.repo.clone());
+
+    tauri::api::shell::open(&app.shell_scope(), url, None).map_err(|err| err.into())
 }
 
 #[tauri::command]
@@ -64,7 +97,48 @@ fn config_add_dir(path: String, state: tauri::State<AppState>) -> Result<(), LpE
 
     conf.add_dir(path)?;
 
-    conf.save()
+    conf.save()?;
+
+    if let Some(watcher) = state.watcher.lock().unwrap().as_ref() {
+        watcher.sync(&conf);
+    }
+
+    Ok(())
+}
+
+#[tauri::command]
+fn config_remove_dir(path: String, state: tauri::State<AppState>) -> Result<(), LpError> {
+    let mut conf = state.config.lock().unwrap();
+
+    conf.remove_dir(path)?;
+
+    conf.save()?;
+
+    if let Some(watcher) = state.watcher.lock().unwrap().as_ref() {
+        watcher.sync(&conf);
+    }
+
+    Ok(())
+}
+
+#[tauri::command]
+fn config_set_walk_globs(
+    exclude_globs: Vec<String>,
+    include_globs: Vec<String>,
+    state: tauri::State<AppState>,
+) -> Result<(), LpError> {
+    let mut conf = state.config.lock().unwrap();
+
+    conf.exclude_globs = exclude_globs;
+    conf.include_globs = include_globs;
+
+    conf.save()?;
+
+    if let Some(watcher) = state.watcher.lock().unwrap().as_ref() {
+        watcher.sync(&conf);
+    }
+
+    Ok(())
 }
 
 #[tauri::command]
@@ -85,33 +159,42 @@ fn get_file_contents(file: String) -> Result<String, LpError> {
     std::fs::read_to_string(file).map_err(|err| err.into())
 }
 
+/// Searches projects, `query` may hold filters, see `query::Query`
 #[tauri::command]
-fn search_query(query: String, state: tauri::State<AppState>) -> Result<Vec<Project>, LpError> {
-    let model = state.search_model.lock().unwrap();
-    let index = state.index.lock().unwrap();
+fn search_query(
+    query: String,
+    state: tauri::State<AppState>,
+) -> Result<search::SearchResponse, LpError> {
+    let (mut response, lexer) = {
+        let model = state.search_model.lock().unwrap();
+        let index = state.index.lock().unwrap();
+
+        (
+            search::search_projects(&query, &model, &index)?,
+            model.lexer.clone(),
+        )
+    };
 
-    let query: Vec<char> = query.chars().into_iter().collect();
+    // READMEs are read without blocking the watcher and re-index
+    search::add_snippets(&mut response.projects, &lexer);
 
-    println!("Query -> {query:?}");
+    Ok(response)
+}
 
-    if query.len() == 0 {
-        return Ok(index.projects());
-    }
+/// How the search model scores the project at `path` for `query`
+#[tauri::command]
+fn explain_search(
+    query: String,
+    path: String,
+    state: tauri::State<AppState>,
+) -> Result<search_model::Explanation, LpError> {
+    let model = state.search_model.lock().unwrap();
 
-    if let Ok(projects) = model.search_query(&query) {
-        let mut result = Vec::new();
-        for p in projects {
-            if let Some(project) = index.projects_map().get(&p.0) {
-                println!("Project -> {name}", name = project.name);
-                println!("\n");
-                result.push(project.clone());
-            }
-        }
+    let query: Vec<char> = query.chars().collect();
 
-        return Ok(result);
-    } else {
-        return Ok(Vec::new());
-    };
+    model
+        .explain(&query, &PathBuf::from(&path))
+        .ok_or(LpError::Error(format!("{path} is not in the search index")))
 }
 
 #[tauri::command]
@@ -136,32 +219,161 @@ fn reload_index(state: tauri::State<AppState>) {
     println!("INDEX RELOADED");
 }
 
+/// Opens the project or workspace member at `path` with `launcher`,
+/// or the launcher configured for its project type
 #[tauri::command]
-fn open_project(path: String) {
-    let shell = if cfg!(windows) { "cmd" } else { "sh" };
+fn open_project(
+    path: String,
+    launcher: Option<String>,
+    state: tauri::State<AppState>,
+) -> Result<(), LpError> {
+    let config = state.config.lock().unwrap();
+    let index = state.index.lock().unwrap();
 
-    let res = Command::new(shell).args(["code", &path]).spawn();
+    let project = index
+        .find_project(&PathBuf::from(&path))
+        .ok_or(LpError::Error(format!("Project not found at {path}")))?;
 
-    if res.is_err() {
-        panic!("Cannot open code");
+    launcher::open(
+        &config.launchers,
+        &launcher::Target::of(project, &path),
+        launcher.as_deref(),
+    )
+}
+
+/// Opens the preferred terminal at the project or workspace member
+/// at `path`, running `command` in it if given
+#[tauri::command]
+fn open_terminal(
+    path: String,
+    command: Option<String>,
+    state: tauri::State<AppState>,
+) -> Result<(), LpError> {
+    let config = state.config.lock().unwrap();
+    let index = state.index.lock().unwrap();
+
+    let project = index
+        .find_project(&PathBuf::from(&path))
+        .ok_or(LpError::Error(format!("Project not found at {path}")))?;
+
+    launcher::open_terminal(
+        &config.launchers,
+        &launcher::Target::of(project, &path),
+        command.as_deref(),
+    )
+}
+
+/// Opens the project or workspace member at `path` in the file manager
+#[tauri::command]
+fn open_file_manager(path: String, state: tauri::State<AppState>) -> Result<(), LpError> {
+    let index = state.index.lock().unwrap();
+
+    let project = index
+        .find_project(&PathBuf::from(&path))
+        .ok_or(LpError::Error(format!("Project not found at {path}")))?;
+
+    launcher::open_file_manager(&launcher::Target::of(project, &path))
+}
+
+/// Runs the task `task_id` of the project or workspace member at `path`,
+/// its output and exit are emitted as `task-output` and `task-exited`
+/// Returns the run id to cancel it with
+#[tauri::command]
+fn run_task(
+    path: String,
+    task_id: String,
+    app: tauri::AppHandle,
+    state: tauri::State<AppState>,
+) -> Result<u64, LpError> {
+    if state
+        .index
+        .lock()
+        .unwrap()
+        .find_project(&PathBuf::from(&path))
+        .is_none()
+    {
+        return Err(LpError::Error(format!("Project not found at {path}")));
     }
+
+    // read again, the task files may have changed since the last index
+    let task = tasks::discover::discover(Path::new(&path))
+        .into_iter()
+        .find(|task| task.id == task_id)
+        .ok_or(LpError::Error(format!("No task {task_id} in {path}")))?;
+
+    state.tasks.start(&task, Path::new(&path), move |event| {
+        if let Err(err) = app.emit_all(event.name(), event) {
+            eprintln!("ERROR: emitting task event : {err}");
+        }
+    })
 }
 
+/// Stops the task started as `run_id`
 #[tauri::command]
-fn re_index(state: tauri::State<AppState>) {
+fn cancel_task(run_id: u64, state: tauri::State<AppState>) -> Result<(), LpError> {
+    state.tasks.cancel(run_id)
+}
+
+/// Names of the launchers projects can be opened with
+#[tauri::command]
+fn list_launchers(state: tauri::State<AppState>) -> Vec<String> {
+    state.config.lock().unwrap().launchers.names()
+}
+
+/// Re-indexes all `project_dirs`
+/// Returns the paths of the projects pruned from the index
+#[tauri::command]
+fn re_index(state: tauri::State<AppState>) -> Result<Vec<String>, LpError> {
     let config = Arc::clone(&state.config);
     let mut indexing = state.is_indexing.lock().unwrap();
     *indexing = true;
     println!("[re_index] Indexing Start");
 
     let c = config.lock().unwrap().clone();
-    let x = std::thread::spawn(move || file_handler::initiate_search(&c)).join();
-    create_search_index();
+    let index = Arc::clone(&state.index);
+
+    // walks into the shared index, which the watcher also updates and saves,
+    // so neither overwrites the other's changes
+    let x = std::thread::spawn(move || {
+        let pruned = file_handler::index_projects(&c, &index);
+
+        index.lock().unwrap().save().map(|_| pruned)
+    })
+    .join();
+
+    // only projects changed since the last run are lexed again
+    if let Ok(Ok(_)) = &x {
+        let mut model = state.search_model.lock().unwrap();
+        let index = state.index.lock().unwrap();
+
+        let update = search::update_search_index(&mut model, &index);
+
+        println!("[re_index] Search index updated {update:?}");
+
+        if !update.is_empty() {
+            let _res = search::save_model(&model);
+        }
+    }
+
     if let Ok(_) = x {
         *indexing = false;
     }
 
     println!("[re_index] Indexing Finished");
+
+    match x {
+        Ok(Ok(pruned)) => Ok(pruned.into_iter().map(|project| project.path).collect()),
+        Ok(Err(err)) => Err(err),
+        Err(_) => Err(LpError::Error(String::from("Indexing failed"))),
+    }
+}
+
+/// Git projects holding work that exists nowhere else
+#[tauri::command]
+fn unsaved_work_report(state: tauri::State<AppState>) -> Vec<git::unsaved::UnsavedWork> {
+    let projects = state.index.lock().unwrap().projects();
+
+    git::unsaved::unsaved_work_report(&projects)
 }
 
 #[tauri::command]
@@ -171,52 +383,112 @@ fn is_indexing(state: tauri::State<AppState>) -> bool {
 
 // Command to get projects
 
+/// Attaches to the console of the shell the app was started from,
+/// release builds have none of their own and commands would print nothing
+#[cfg(windows)]
+fn attach_console() {
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn AttachConsole(process_id: u32) -> i32;
+    }
+
+    const ATTACH_PARENT_PROCESS: u32 = u32::MAX;
+
+    // fails when started from Explorer, there is no console to print to then
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
+
 fn main() {
     dotenv::dotenv().ok();
 
+    let context = tauri::generate_context!();
+
+    // a command runs without opening a window, see `cli`
+    if let Some(cli_config) = context.config().tauri.cli.as_ref() {
+        #[cfg(windows)]
+        if std::env::args().len() > 1 {
+            attach_console();
+        }
+
+        match tauri::api::cli::get_matches(cli_config, context.package_info()) {
+            Ok(matches) if cli::is_command(&matches) => std::process::exit(cli::run(&matches)),
+            Ok(_) => {}
+            Err(err) => {
+                let args: Vec<String> = std::env::args().skip(1).collect();
+
+                eprintln!("ERROR: {err}");
+
+                if cli::names_command(cli_config, &args) {
+                    std::process::exit(2);
+                }
+            }
+        }
+    }
+
     let config = Config::load();
     {
-        let _ = std::thread::spawn(move || file_handler::initiate_search(&config)).join();
-        create_search_index();
+        let c = config.clone();
+        let _ = std::thread::spawn(move || file_handler::initiate_search(&c)).join();
     }
 
-    let model = match search_model::load_model() {
-        Ok(model) => model,
-        Err(_) => search_model::Model::default(),
-    };
+    let index = indexer::Index::load_or_default();
+
+    let (model, _) = search::load_search_index(&config, &index);
 
     tauri::Builder::default()
         .manage(AppState {
-            config: Arc::new(Mutex::new(Config::load())),
-            index: Arc::new(Mutex::new(indexer::Index::load_or_default())),
+            config: Arc::new(Mutex::new(config)),
+            index: Arc::new(Mutex::new(index)),
             search_model: Arc::new(Mutex::new(model)),
             is_indexing: Arc::new(Mutex::new(false)),
+            watcher: Arc::new(Mutex::new(None)),
+            tasks: tasks::runner::TaskRunner::default(),
         })
         .invoke_handler(tauri::generate_handler![
             greet,
             config_add_dir,
+            config_remove_dir,
+            config_set_walk_globs,
             get_config,
             get_projects,
             get_file_contents,
             reload_index,
             re_index,
             open_repo_url,
+            get_repo_links,
             is_indexing,
             open_project,
+            list_launchers,
+            open_terminal,
+            open_file_manager,
+            run_task,
+            cancel_task,
             search_query,
+            explain_search,
+            unsaved_work_report,
         ])
         .setup(|app| {
-            match app.get_cli_matches() {
-                Ok(matches) => {
-                    println!("{matches:?}");
+            let state = app.state::<AppState>();
+
+            match ProjectWatcher::start(
+                app.handle(),
+                Arc::clone(&state.config),
+                Arc::clone(&state.index),
+                Arc::clone(&state.search_model),
+            ) {
+                Ok(watcher) => {
+                    *state.watcher.lock().unwrap() = Some(watcher);
                 }
                 Err(err) => {
-                    println!("{err:?}");
+                    eprintln!("ERROR: {err}");
                 }
             }
+
             Ok(())
         })
-        .run(tauri::generate_context!())
+        .run(context)
         .expect("error while running tauri application");
 }
 
@@ -224,14 +496,20 @@ fn main() {
 mod main_tests {
     use std::collections::HashMap;
 
-    use crate::open_project;
+    use crate::{config::Config, launcher};
 
     #[ignore]
     #[test]
     fn it_should_open_project() {
         let path = "/Users/s_mash/Documents/projects/rust/local_projects";
 
-        let _x = open_project(path.to_string());
+        let target = launcher::Target {
+            name: "local_projects",
+            path,
+            project_type: None,
+        };
+
+        let _x = launcher::open(&Config::load().launchers, &target, None);
     }
 
     #[ignore]
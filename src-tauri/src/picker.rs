@@ -0,0 +1,183 @@
+// Line per project output for fzf/rofi like pickers, and shell functions using it
+
+use crate::{errors::lp_error::LpError, file_handler::Project, git::status::GitHead};
+
+/// Template of `pick` lines, the path is last so helpers can cut it off
+pub const DEFAULT_TEMPLATE: &str = "{name}\t{type}\t{branch}\t{path}";
+
+/// Placeholders of a template, each replaced by a field of the project
+pub const PLACEHOLDERS: [&str; 4] = ["name", "type", "path", "branch"];
+
+/// `template` filled in for `project`
+/// `\t` in the template is a tab, tabs and line breaks
+/// in the fields are replaced by spaces to keep a line per project
+pub fn render(template: &str, project: &Project) -> Result<String, LpError> {
+    let mut line = String::new();
+    let mut rest = template;
+
+    while let Some(start) = rest.find(['{', '\\']) {
+        line.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        if let Some(after) = rest.strip_prefix("\\t") {
+            line.push('\t');
+            rest = after;
+            continue;
+        }
+
+        if let Some(after) = rest.strip_prefix('\\') {
+            line.push('\\');
+            rest = after;
+            continue;
+        }
+
+        let end = rest
+            .find('}')
+            .ok_or_else(|| LpError::Error(format!("Unclosed placeholder in {template:?}")))?;
+
+        let value = field(&rest[1..end], project)?;
+
+        line.extend(value.chars().map(|c| match c {
+            '\t' | '\n' | '\r' | '\0' => ' ',
+            c => c,
+        }));
+
+        rest = &rest[end + 1..];
+    }
+
+    line.push_str(rest);
+
+    Ok(line)
+}
+
+fn field(name: &str, project: &Project) -> Result<String, LpError> {
+    let value = match name {
+        "name" => project.name.clone(),
+        "type" => format!("{:?}", project.project_type),
+        "path" => project.path.clone(),
+        "branch" => project
+            .git_status
+            .as_ref()
+            .map(|status| match &status.head {
+                GitHead::Branch(name) | GitHead::Unborn(name) => name.clone(),
+                GitHead::Detached(hash) => hash.clone(),
+            })
+            .unwrap_or_default(),
+        _ => {
+            return Err(LpError::Error(format!(
+                "Unknown placeholder {{{name}}}, expected one of {}",
+                PLACEHOLDERS.join(", ")
+            )))
+        }
+    };
+
+    Ok(value)
+}
+
+/// Shell function `name`, which picks a project with fzf and `cd`s into it
+/// Its arguments are passed to `pick` as filters, a single match is taken
+/// without asking
+pub fn shell_init(shell: &str, name: &str, exe: &str) -> Result<String, LpError> {
+    let pick = format!(
+        "pick --print0 --template '{}'",
+        DEFAULT_TEMPLATE.replace('\t', "\\t")
+    );
+
+    let fzf = "fzf --read0 --delimiter '\\t' --with-nth 1..3 --select-1 --exit-0";
+
+    let script = match shell {
+        "bash" | "zsh" => format!(
+            "{name}() {{\n    \
+                local dir\n    \
+                dir=\"$(command {exe} {pick} -- \"$@\" | {fzf} | cut -f4)\" \
+                && [ -n \"$dir\" ] && cd -- \"$dir\"\n\
+            }}\n",
+            exe = sh_quote(exe),
+        ),
+        "fish" => format!(
+            "function {name}\n    \
+                set -l dir (command {exe} {pick} -- $argv | {fzf} | cut -f4)\n    \
+                and test -n \"$dir\"\n    \
+                and cd -- $dir\n\
+            end\n",
+            exe = fish_quote(exe),
+        ),
+        _ => {
+            return Err(LpError::Error(format!(
+                "Unsupported shell {shell}, expected bash, zsh or fish"
+            )))
+        }
+    };
+
+    Ok(script)
+}
+
+fn sh_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+fn fish_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\\', r"\\").replace('\'', r"\'"))
+}
+
+#[cfg(test)]
+mod picker_tests {
+    use super::{render, shell_init, DEFAULT_TEMPLATE};
+    use crate::{
+        file_handler::{Project, ProjectType},
+        git::status::{GitHead, GitStatus},
+    };
+
+    #[test]
+    fn it_should_render_a_line_per_project() {
+        let mut project = Project::base(
+            String::from("local\tprojects"),
+            String::from("/p/local_projects"),
+            None,
+            ProjectType::Rust,
+        );
+
+        assert_eq!(
+            render(DEFAULT_TEMPLATE, &project).unwrap(),
+            "local projects\tRust\t\t/p/local_projects"
+        );
+
+        project.git_status = Some(GitStatus {
+            head: GitHead::Branch(String::from("main")),
+            staged: 0,
+            unstaged: 0,
+            untracked: 0,
+            conflicted: 0,
+            upstream: None,
+            upstream_url: None,
+            upstream_branch: None,
+            ahead: 0,
+            behind: 0,
+            last_commit: None,
+            stash_count: 0,
+        });
+
+        assert_eq!(
+            render(r"{branch}\t{path}", &project).unwrap(),
+            "main\t/p/local_projects"
+        );
+
+        assert!(render("{size}", &project).is_err());
+        assert!(render("{name", &project).is_err());
+    }
+
+    #[test]
+    fn it_should_generate_cd_helpers() {
+        let bash = shell_init("bash", "lpcd", "/opt/it's/local_projects").unwrap();
+
+        assert!(bash.starts_with("lpcd() {"));
+        assert!(bash.contains(r"command '/opt/it'\''s/local_projects' pick --print0"));
+
+        let fish = shell_init("fish", "lpcd", "local_projects").unwrap();
+
+        assert!(fish.starts_with("function lpcd\n"));
+        assert!(fish.contains("-- $argv |"));
+
+        assert!(shell_init("nu", "lpcd", "local_projects").is_err());
+    }
+}
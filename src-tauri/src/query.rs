@@ -0,0 +1,538 @@
+// Query language of `search_query`
+//
+// parser lexer                 free text, ranked by the search model
+// "foo bar"                    phrase in name, description or path
+// type:rust lang:TypeScript    filters, see `Filter`
+// -archived  NOT archived      negation
+// a OR b, a AND b, (a b)       boolean operators, clauses are ANDed by default
+
+use std::{
+    ops::Range,
+    time::{Duration, SystemTime},
+};
+
+use crate::{errors::lp_error::LpError, file_handler::Project, git::status::GitHead};
+
+/// Parsed search query
+#[derive(Debug, Clone, PartialEq)]
+pub enum Query {
+    /// Matches every project
+    All,
+    And(Vec<Query>),
+    Or(Vec<Query>),
+    Not(Box<Query>),
+    /// Consecutive free words, a project matches if the search model
+    /// finds any of them in it
+    /// `span` is the range of chars the words cover in the query
+    Text {
+        text: String,
+        span: Range<usize>,
+    },
+    /// Quoted words, matched as is in name, description or path
+    Phrase(String),
+    Filter(Filter),
+}
+
+/// Condition on a `Project` field
+#[derive(Debug, Clone, PartialEq)]
+pub enum Filter {
+    /// `type:rust`, `ProjectType` by name
+    Type(String),
+    /// `lang:typescript`, a language in `language_map`
+    Language(String),
+    /// `remote:github`, part of a git remote url
+    Remote(String),
+    /// `name:"foo bar"`, part of the name
+    Name(String),
+    /// `path:work`, part of the path
+    Path(String),
+    /// `branch:main`, part of the current branch
+    Branch(String),
+    /// `dirty:true`, has uncommitted changes
+    Dirty(bool),
+    /// `modified:<30d` modified within `age`, `modified:>1y` before it
+    Modified { within: bool, age: Duration },
+}
+
+impl Filter {
+    /// Parses `field:value`, `None` for unknown fields
+    fn parse(field: &str, value: &str) -> Option<Result<Filter, LpError>> {
+        let text = value.to_lowercase();
+
+        let filter = match field.to_lowercase().as_str() {
+            "type" => Filter::Type(text),
+            "lang" | "language" => Filter::Language(text),
+            "remote" | "git" => Filter::Remote(text),
+            "name" => Filter::Name(text),
+            "path" => Filter::Path(text),
+            "branch" => Filter::Branch(text),
+            "dirty" => match text.as_str() {
+                "true" | "yes" => Filter::Dirty(true),
+                "false" | "no" => Filter::Dirty(false),
+                _ => return Some(Err(invalid(field, value, "true or false"))),
+            },
+            "modified" => match parse_age(&text) {
+                Some((within, age)) => Filter::Modified { within, age },
+                None => return Some(Err(invalid(field, value, "e.g. <30d or >1y"))),
+            },
+            _ => return None,
+        };
+
+        Some(Ok(filter))
+    }
+
+    pub fn matches(&self, project: &Project, now: SystemTime) -> bool {
+        match self {
+            Filter::Type(name) => format!("{:?}", project.project_type).to_lowercase() == *name,
+            Filter::Language(name) => project
+                .language_map
+                .keys()
+                .any(|lang| lang.to_lowercase() == *name),
+            Filter::Remote(part) => project
+                .git
+                .iter()
+                .any(|url| url.to_lowercase().contains(part)),
+            Filter::Name(part) => project.name.to_lowercase().contains(part),
+            Filter::Path(part) => project.path.to_lowercase().contains(part),
+            Filter::Branch(part) => match project.git_status.as_ref().map(|s| &s.head) {
+                Some(GitHead::Branch(branch)) | Some(GitHead::Unborn(branch)) => {
+                    branch.to_lowercase().contains(part)
+                }
+                _ => false,
+            },
+            Filter::Dirty(dirty) => {
+                let has_changes = project
+                    .git_status
+                    .as_ref()
+                    .is_some_and(|s| s.staged + s.unstaged + s.untracked + s.conflicted > 0);
+
+                has_changes == *dirty
+            }
+            Filter::Modified { within, age } => {
+                let modified_within = now
+                    .duration_since(SystemTime::from(project.last_modified))
+                    .map_or(true, |elapsed| elapsed <= *age);
+
+                modified_within == *within
+            }
+        }
+    }
+}
+
+fn invalid(field: &str, value: &str, expected: &str) -> LpError {
+    LpError::Error(format!(
+        "invalid value `{value}` for `{field}:`, expected {expected}"
+    ))
+}
+
+/// Parses `<30d`, `>2w` or `6m`, no comparison means within
+/// Units are h(ours), d(ays), w(eeks), m(onths) and y(ears)
+fn parse_age(value: &str) -> Option<(bool, Duration)> {
+    let (within, rest) = if let Some(rest) = value.strip_prefix('>') {
+        (false, rest)
+    } else {
+        (true, value.strip_prefix('<').unwrap_or(value))
+    };
+
+    let rest = rest.strip_prefix('=').unwrap_or(rest);
+
+    let unit = rest.chars().last()?;
+    let count: u64 = rest[..rest.len() - unit.len_utf8()].parse().ok()?;
+
+    let hours = match unit {
+        'h' => 1,
+        'd' => 24,
+        'w' => 24 * 7,
+        'm' => 24 * 30,
+        'y' => 24 * 365,
+        _ => return None,
+    };
+
+    let secs = count.checked_mul(hours * 3600)?;
+
+    Some((within, Duration::from_secs(secs)))
+}
+
+/// Whether an invalid `value` of `field` is the start of a valid one
+fn is_partial_value(field: &str, value: &str) -> bool {
+    let value = value.to_lowercase();
+
+    match field.to_lowercase().as_str() {
+        "dirty" => ["true", "yes", "false", "no"]
+            .iter()
+            .any(|valid| valid.starts_with(&value)),
+        "modified" => {
+            let count = value.strip_prefix(['<', '>']).unwrap_or(&value);
+            let count = count.strip_prefix('=').unwrap_or(count);
+
+            count.is_empty() || count.parse::<u64>().is_ok()
+        }
+        _ => false,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    LParen,
+    RParen,
+    And,
+    Or,
+    Not,
+    Word(String, Range<usize>),
+    Phrase(String),
+    Field(String, String),
+}
+
+/// Splits `query` into tokens
+/// Unclosed quotes run to the end of the query
+fn tokenize(query: &[char]) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    let quoted = |i: &mut usize| -> String {
+        let start = *i + 1;
+        let end = query[start..]
+            .iter()
+            .position(|c| *c == '"')
+            .map_or(query.len(), |p| start + p);
+
+        *i = (end + 1).min(query.len());
+
+        query[start..end].iter().collect()
+    };
+
+    while i < query.len() {
+        let c = query[i];
+
+        if c.is_whitespace() {
+            i += 1;
+        } else if c == '(' {
+            tokens.push(Token::LParen);
+            i += 1;
+        } else if c == ')' {
+            tokens.push(Token::RParen);
+            i += 1;
+        } else if c == '-' && query.get(i + 1).is_some_and(|n| !n.is_whitespace()) {
+            tokens.push(Token::Not);
+            i += 1;
+        } else if c == '"' {
+            tokens.push(Token::Phrase(quoted(&mut i)));
+        } else {
+            let start = i;
+
+            while i < query.len() && !query[i].is_whitespace() && !"()\"".contains(query[i]) {
+                i += 1;
+            }
+
+            let word: String = query[start..i].iter().collect();
+
+            match word.split_once(':') {
+                Some((field, value)) if !field.is_empty() => {
+                    let value = if value.is_empty() && query.get(i) == Some(&'"') {
+                        quoted(&mut i)
+                    } else {
+                        value.to_string()
+                    };
+
+                    tokens.push(Token::Field(field.to_string(), value));
+                }
+                _ => match word.as_str() {
+                    "AND" => tokens.push(Token::And),
+                    "OR" => tokens.push(Token::Or),
+                    "NOT" => tokens.push(Token::Not),
+                    _ => tokens.push(Token::Word(word, start..i)),
+                },
+            }
+        }
+    }
+
+    tokens
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn parse_or(&mut self) -> Result<Query, LpError> {
+        let mut clauses = vec![self.parse_and()?];
+
+        while self.peek() == Some(&Token::Or) {
+            self.next();
+            clauses.push(self.parse_and()?);
+        }
+
+        Ok(simplify(clauses, Query::Or))
+    }
+
+    /// Consecutive free words are merged into one `Query::Text`,
+    /// unless separated by an explicit `AND`
+    fn parse_and(&mut self) -> Result<Query, LpError> {
+        let mut clauses: Vec<Query> = Vec::new();
+        let mut explicit_and = false;
+
+        loop {
+            match self.peek() {
+                None | Some(Token::RParen) | Some(Token::Or) => break,
+                Some(Token::And) => {
+                    self.next();
+                    explicit_and = true;
+                    continue;
+                }
+                _ => {}
+            }
+
+            let clause = self.parse_unary()?;
+
+            if let (
+                Some(Query::Text { text, span }),
+                Query::Text {
+                    text: next_text,
+                    span: next_span,
+                },
+            ) = (clauses.last_mut(), &clause)
+            {
+                if !explicit_and {
+                    text.push(' ');
+                    text.push_str(next_text);
+                    span.end = next_span.end;
+                    continue;
+                }
+            }
+
+            explicit_and = false;
+
+            if clause != Query::All {
+                clauses.push(clause);
+            }
+        }
+
+        Ok(simplify(clauses, Query::And))
+    }
+
+    fn parse_unary(&mut self) -> Result<Query, LpError> {
+        let Some(token) = self.next() else {
+            return Ok(Query::All);
+        };
+
+        match token {
+            Token::Not => match self.parse_unary()? {
+                Query::All => Ok(Query::All),
+                query => Ok(Query::Not(Box::new(query))),
+            },
+            Token::LParen => {
+                let query = self.parse_or()?;
+
+                // a missing `)` closes at the end of the query
+                if self.peek() == Some(&Token::RParen) {
+                    self.next();
+                }
+
+                Ok(query)
+            }
+            Token::Word(text, span) => Ok(Query::Text { text, span }),
+            Token::Phrase(phrase) if phrase.trim().is_empty() => Ok(Query::All),
+            Token::Phrase(phrase) => Ok(Query::Phrase(phrase.to_lowercase())),
+            // while typing `type:` there is no value yet
+            Token::Field(_, value) if value.is_empty() => Ok(Query::All),
+            Token::Field(field, value) => match Filter::parse(&field, &value) {
+                // while typing `dirty:t` or `modified:<3` the value is not complete yet
+                Some(Err(_)) if is_partial_value(&field, &value) => Ok(Query::All),
+                Some(filter) => Ok(Query::Filter(filter?)),
+                None => Ok(Query::Phrase(format!("{field}:{value}").to_lowercase())),
+            },
+            // stray operators and `)` are skipped
+            Token::And | Token::Or | Token::RParen => Ok(Query::All),
+        }
+    }
+}
+
+fn simplify(mut clauses: Vec<Query>, combine: fn(Vec<Query>) -> Query) -> Query {
+    match clauses.len() {
+        0 => Query::All,
+        1 => clauses.remove(0),
+        _ => combine(clauses),
+    }
+}
+
+impl Query {
+    pub fn parse(query: &str) -> Result<Query, LpError> {
+        let chars: Vec<char> = query.chars().collect();
+
+        let mut parser = Parser {
+            tokens: tokenize(&chars),
+            pos: 0,
+        };
+
+        let mut clauses = vec![parser.parse_or()?];
+
+        // unmatched `)`, keep parsing after it
+        while parser.pos < parser.tokens.len() {
+            parser.next();
+            clauses.push(parser.parse_or()?);
+        }
+
+        clauses.retain(|c| *c != Query::All);
+
+        Ok(simplify(clauses, Query::And))
+    }
+
+    /// Whether `project` matches, `text_matches` tells whether
+    /// the project matches the free text of a `Query::Text`
+    pub fn matches<F>(&self, project: &Project, now: SystemTime, text_matches: &F) -> bool
+    where
+        F: Fn(&str, &Project) -> bool,
+    {
+        match self {
+            Query::All => true,
+            Query::And(clauses) => clauses
+                .iter()
+                .all(|c| c.matches(project, now, text_matches)),
+            Query::Or(clauses) => clauses
+                .iter()
+                .any(|c| c.matches(project, now, text_matches)),
+            Query::Not(query) => !query.matches(project, now, text_matches),
+            Query::Text { text, .. } => text_matches(text, project),
+            Query::Phrase(phrase) => {
+                project.name.to_lowercase().contains(phrase)
+                    || project.path.to_lowercase().contains(phrase)
+                    || project
+                        .description
+                        .as_ref()
+                        .is_some_and(|d| d.to_lowercase().contains(phrase))
+            }
+            Query::Filter(filter) => filter.matches(project, now),
+        }
+    }
+
+    /// `Query::Text` clauses, each with whether it is negated
+    pub fn texts(&self) -> Vec<(&str, &Range<usize>, bool)> {
+        let mut texts = Vec::new();
+        self.collect_texts(false, &mut texts);
+        texts
+    }
+
+    fn collect_texts<'a>(
+        &'a self,
+        negated: bool,
+        texts: &mut Vec<(&'a str, &'a Range<usize>, bool)>,
+    ) {
+        match self {
+            Query::And(clauses) | Query::Or(clauses) => {
+                for clause in clauses {
+                    clause.collect_texts(negated, texts);
+                }
+            }
+            Query::Not(query) => query.collect_texts(!negated, texts),
+            Query::Text { text, span } => texts.push((text, span, negated)),
+            _ => {}
+        }
+    }
+
+    /// Words of the query that aren't negated, to rank results by
+    pub fn ranking_text(&self) -> String {
+        let mut words = Vec::new();
+        self.collect_ranking_words(false, &mut words);
+        words.join(" ")
+    }
+
+    fn collect_ranking_words<'a>(&'a self, negated: bool, words: &mut Vec<&'a str>) {
+        match self {
+            Query::And(clauses) | Query::Or(clauses) => {
+                for clause in clauses {
+                    clause.collect_ranking_words(negated, words);
+                }
+            }
+            Query::Not(query) => query.collect_ranking_words(!negated, words),
+            Query::Text { text, .. } if !negated => words.push(text),
+            Query::Phrase(phrase) if !negated => words.push(phrase),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod query_tests {
+    use std::time::Duration;
+
+    use super::{Filter, Query};
+
+    fn text(text: &str, start: usize, end: usize) -> Query {
+        Query::Text {
+            text: text.to_string(),
+            span: start..end,
+        }
+    }
+
+    #[test]
+    fn it_should_parse_filters_negation_phrases_and_operators() {
+        let query = Query::parse(
+            r#"type:rust lang:TypeScript remote:github modified:<30d dirty:true name:"foo bar" -archived"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            query,
+            Query::And(vec![
+                Query::Filter(Filter::Type(String::from("rust"))),
+                Query::Filter(Filter::Language(String::from("typescript"))),
+                Query::Filter(Filter::Remote(String::from("github"))),
+                Query::Filter(Filter::Modified {
+                    within: true,
+                    age: Duration::from_secs(30 * 24 * 3600),
+                }),
+                Query::Filter(Filter::Dirty(true)),
+                Query::Filter(Filter::Name(String::from("foo bar"))),
+                Query::Not(Box::new(text("archived", 81, 89))),
+            ])
+        );
+
+        assert_eq!(
+            Query::parse("http client (cli OR tui) type:").unwrap(),
+            Query::And(vec![
+                text("http client", 0, 11),
+                Query::Or(vec![text("cli", 13, 16), text("tui", 20, 23)]),
+            ])
+        );
+
+        assert_eq!(
+            Query::parse("parser AND lexer").unwrap(),
+            Query::And(vec![text("parser", 0, 6), text("lexer", 11, 16)])
+        );
+
+        assert_eq!(Query::parse("  ").unwrap(), Query::All);
+        assert!(Query::parse("dirty:maybe").is_err());
+        assert!(Query::parse("dirty:truee").is_err());
+        assert!(Query::parse("modified:<soon").is_err());
+        assert!(Query::parse("modified:<99999999999999999y").is_err());
+    }
+
+    #[test]
+    fn it_should_skip_filters_still_being_typed() {
+        for partial in [
+            "dirty:t",
+            "dirty:FA",
+            "modified:<",
+            "modified:>=",
+            "modified:<3",
+        ] {
+            assert_eq!(Query::parse(partial).unwrap(), Query::All, "{partial}");
+        }
+
+        assert_eq!(
+            Query::parse("parser modified:<3").unwrap(),
+            text("parser", 0, 6)
+        );
+    }
+}
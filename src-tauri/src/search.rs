@@ -1,130 +1,508 @@
-use std::fs::File;
-use std::io::BufWriter;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::path::{Path, PathBuf};
 use std::time::SystemTime;
 
-use crate::config::get_config_dir;
-use crate::search_model::Model;
-use serde_json;
+use crate::config::{get_config_dir, Config};
+use crate::errors::lp_error::LpError;
+use crate::file_handler::Project;
+use crate::indexer::Index;
+use crate::lexer::LexerConfig;
+use crate::query::Query;
+use crate::search_model::{index_file, load_model, DocumentFields, Field, Model};
+use crate::snippet::{snippet, Snippet};
+use serde::Serialize;
+
+/// Hits that get snippets, reading READMEs for all of them is too slow
+const SNIPPET_HITS: usize = 20;
+
+/// Response to a search query from the frontend
+#[derive(Serialize, Debug, Default)]
+pub struct SearchResponse {
+    pub projects: Vec<SearchHit>,
+    /// The query with unknown words corrected, if any were close
+    pub suggestion: Option<String>,
+    pub facets: Facets,
+}
 
-pub fn create_search_index() {
-    let mut config = get_config_dir();
+/// A project matching a search, with why it matched
+#[derive(Serialize, Debug)]
+pub struct SearchHit {
+    #[serde(flatten)]
+    pub project: Project,
+    /// Rank from the search model, `None` if only filters matched
+    pub score: Option<f32>,
+    /// Fields the free text of the query matched in
+    pub matched_fields: Vec<Field>,
+    /// Description and README around the matched terms
+    pub snippets: Vec<Snippet>,
+    /// Terms of the search model the query matched
+    #[serde(skip)]
+    terms: HashSet<String>,
+}
 
-    config.push("index.json");
+/// Model hits of a project and its workspace members
+#[derive(Default)]
+struct ProjectMatch {
+    score: f32,
+    fields: Vec<Field>,
+    terms: HashSet<String>,
+}
 
-    let mut model = Model::default();
+/// Counts over the matching projects, to narrow a search down
+#[derive(Serialize, Debug, Default)]
+pub struct Facets {
+    /// Matching projects per `ProjectType`
+    pub project_types: BTreeMap<String, usize>,
+    /// Matching projects per language in their `language_map`
+    pub languages: BTreeMap<String, usize>,
+}
 
-    if let Ok(file) = std::fs::File::open(config) {
-        let json: serde_json::Value = serde_json::from_reader(file).unwrap();
+impl Facets {
+    fn count(hits: &[SearchHit]) -> Self {
+        let mut facets = Facets::default();
 
-        let projects = json.get("projects").unwrap();
+        for SearchHit { project, .. } in hits {
+            *facets
+                .project_types
+                .entry(format!("{:?}", project.project_type))
+                .or_insert(0) += 1;
 
-        for (key, value) in projects.as_object().unwrap() {
-            let path = key.as_str();
+            for language in project.language_map.keys() {
+                *facets.languages.entry(language.clone()).or_insert(0) += 1;
+            }
+        }
 
-            let name = value.get("name").unwrap().as_str().unwrap();
+        facets
+    }
+}
 
-            let project_type = match value.get("project_type") {
-                Some(project_type) => {
-                    if !project_type.is_null() {
-                        let value = project_type.as_str().unwrap();
-                        String::from(value)
-                    } else {
-                        String::from("")
-                    }
-                }
-                None => String::from(""),
-            };
-
-            let description = match value.get("description") {
-                Some(des) => {
-                    if !des.is_null() {
-                        let value = des.as_str().unwrap();
-                        String::from(value)
-                    } else {
-                        String::from("")
-                    }
-                }
-                None => String::from(""),
-            };
-
-            let doc_contents = match value.get("documentation_file") {
-                Some(file) => {
-                    if file.is_null() {
-                        String::from("")
-                    } else {
-                        let path = PathBuf::from(file.as_str().unwrap());
-
-                        match std::fs::read_to_string(path) {
-                            Ok(content) => content,
-                            Err(_) => String::from(""),
-                        }
-                    }
-                }
-                None => String::from(""),
-            };
-
-            let last_modified: SystemTime = match value.get("last_modified") {
-                Some(modified) => {
-                    if !modified.is_null() {
-                        let _secs = modified.get("secs_since_epoch").unwrap().as_u64().unwrap();
-                        let _nanos = modified.get("nanos_since_epoch");
-
-                        SystemTime::now()
-                    } else {
-                        SystemTime::now()
+/// Runs `query` against the indexed projects, see `query::Query`
+/// Free text is ranked by the search `model`, without any the
+/// matching projects are in `index` order
+/// Snippets are left empty, `add_snippets` reads the READMEs for them
+/// and should be called after the model and index locks are released
+pub fn search_projects(
+    query: &str,
+    model: &Model,
+    index: &Index,
+) -> Result<SearchResponse, LpError> {
+    let parsed = Query::parse(query)?;
+
+    let chars: Vec<char> = query.chars().collect();
+
+    // text for the model, a word at the very end of the query
+    // is still being typed and also matches as a prefix
+    let model_query = |text: &str, at_end: bool| -> Vec<char> {
+        let mut text: Vec<char> = text.chars().collect();
+
+        if !at_end {
+            text.push(' ');
+        }
+
+        text
+    };
+
+    // hits of the model, with workspace members grouped under their project
+    let project_hits = |text: Vec<char>| -> HashMap<String, ProjectMatch> {
+        let mut matches: HashMap<String, ProjectMatch> = HashMap::new();
+
+        for hit in model.search_hits(&text, usize::MAX) {
+            if let Some(project) = index.find_project(&hit.path) {
+                let found = matches.entry(project.path.clone()).or_default();
+
+                found.score = found.score.max(hit.score);
+
+                for field in hit.fields {
+                    if !found.fields.contains(&field) {
+                        found.fields.push(field);
                     }
                 }
-                None => SystemTime::now(),
-            };
 
-            let mut content: String = String::new();
+                found.terms.extend(hit.terms);
+            }
+        }
 
-            // Repeat name. project_type to increase weightage
+        matches
+    };
 
-            for _ in 1..10 {
-                content.push_str(&format!("{name} "));
-            }
+    let texts = parsed.texts();
 
-            content.push('\n');
+    let mut text_hits: HashMap<&str, HashSet<String>> = HashMap::new();
+    let mut suggestions = Vec::new();
 
-            for _ in 0..3 {
-                content.push_str(&format!("{project_type} "));
-            }
+    for (text, span, _) in texts.iter() {
+        let text_query = model_query(text, span.end == chars.len());
+
+        if let Some(suggestion) = model.did_you_mean(&text_query) {
+            suggestions.push((span.start..span.end, suggestion));
+        }
 
-            content.push('\n');
+        if !text_hits.contains_key(text) {
+            text_hits.insert(text, project_hits(text_query).into_keys().collect());
+        }
+    }
 
-            for _ in 1..5 {
-                content.push_str(&format!("{description}"));
+    let text_matches = |text: &str, project: &Project| {
+        text_hits
+            .get(text)
+            .is_some_and(|hits| hits.contains(&project.path))
+    };
+
+    let now = SystemTime::now();
+
+    let ranking_text = parsed.ranking_text();
+
+    let mut ranked = if ranking_text.is_empty() {
+        HashMap::new()
+    } else {
+        let at_end = texts
+            .iter()
+            .any(|(_, span, negated)| !negated && span.end == chars.len());
+
+        project_hits(model_query(&ranking_text, at_end))
+    };
+
+    let mut projects: Vec<SearchHit> = index
+        .projects()
+        .into_iter()
+        .filter(|p| parsed.matches(p, now, &text_matches))
+        .map(|project| {
+            let found = ranked.remove(&project.path);
+
+            SearchHit {
+                score: found.as_ref().map(|f| f.score),
+                matched_fields: found.as_ref().map(|f| f.fields.clone()).unwrap_or_default(),
+                snippets: Vec::new(),
+                terms: found.map(|f| f.terms).unwrap_or_default(),
+                project,
             }
+        })
+        .collect();
 
-            content.push('\n');
+    // stable, unscored projects stay in `index` order after the rest
+    projects.sort_by(|a, b| {
+        let score = |hit: &SearchHit| hit.score.unwrap_or(f32::MIN);
 
-            content.push_str(&doc_contents);
+        score(b).total_cmp(&score(a))
+    });
 
-            let content: Vec<char> = content.chars().into_iter().collect();
+    let suggestion = (!suggestions.is_empty()).then(|| {
+        let mut corrected = chars.clone();
 
-            model.add_document(PathBuf::from(path), last_modified, &content.as_slice());
+        for (span, suggestion) in suggestions.iter().rev() {
+            corrected.splice(span.clone(), suggestion.chars());
         }
 
-        let mut index_path = get_config_dir();
+        corrected.into_iter().collect()
+    });
 
-        index_path.push("search-index.json");
+    Ok(SearchResponse {
+        facets: Facets::count(&projects),
+        projects,
+        suggestion,
+    })
+}
 
-        let _res = save_model_as_json(&model, &index_path);
+/// Fills in the snippets of the first `SNIPPET_HITS` of `hits`
+/// `lexer` is the config of the model that found them
+pub fn add_snippets(hits: &mut [SearchHit], lexer: &LexerConfig) {
+    for hit in hits.iter_mut().take(SNIPPET_HITS) {
+        hit.snippets = project_snippets(&hit.project, &hit.matched_fields, &hit.terms, lexer);
     }
 }
 
-fn save_model_as_json(model: &Model, index_path: &Path) -> Result<(), ()> {
-    println!("Saving {index_path:?}...");
+/// Snippets of the description and README of `project`
+/// around `terms`, for the `fields` they matched in
+fn project_snippets(
+    project: &Project,
+    fields: &[Field],
+    terms: &HashSet<String>,
+    lexer: &LexerConfig,
+) -> Vec<Snippet> {
+    let mut snippets = Vec::new();
+
+    if fields.contains(&Field::Description) {
+        if let Some(description) = &project.description {
+            snippets.extend(snippet(Field::Description, description, terms, lexer));
+        }
+    }
 
-    let index_file = File::create(index_path).map_err(|err| {
-        eprintln!("ERROR: could not create index file {index_path:?}: {err}");
-    })?;
+    if fields.contains(&Field::Documentation) {
+        let documentation = project
+            .documentation_file
+            .as_ref()
+            .and_then(|f| std::fs::read_to_string(f).ok());
+
+        if let Some(documentation) = documentation {
+            snippets.extend(snippet(Field::Documentation, &documentation, terms, lexer));
+        }
+    }
+
+    snippets
+}
+
+/// Changes `update_search_index` made to the search model, in projects
+#[derive(Serialize, Debug, Default, PartialEq)]
+pub struct SearchIndexUpdate {
+    pub added: usize,
+    pub updated: usize,
+    /// Projects and workspace members no longer in the index
+    pub removed: usize,
+}
+
+impl SearchIndexUpdate {
+    pub fn is_empty(&self) -> bool {
+        *self == SearchIndexUpdate::default()
+    }
+}
+
+/// `read_search_index`, saving the model if it was not up to date
+pub fn load_search_index(config: &Config, index: &Index) -> (Model, SearchIndexUpdate) {
+    let (model, update) = read_search_index(config, index);
+
+    if !update.is_empty() {
+        let _res = save_model(&model);
+    }
+
+    (model, update)
+}
+
+/// Loads the search model and brings it up to date with `index`,
+/// without saving it
+/// A model lexed differently than set in `config` is rebuilt from scratch
+pub fn read_search_index(config: &Config, index: &Index) -> (Model, SearchIndexUpdate) {
+    let mut model = match load_model() {
+        Ok(model) if model.lexer == config.lexer => model,
+        Ok(_) => {
+            eprintln!("Lexer config changed, rebuilding search index");
+            Model::default()
+        }
+        Err(_) => Model::default(),
+    };
+
+    model.lexer = config.lexer.clone();
+    model.params = config.search_params.clone();
+
+    let update = update_search_index(&mut model, index);
+
+    eprintln!("Search index updated {update:?}");
 
-    serde_json::to_writer(BufWriter::new(index_file), &model).map_err(|err| {
-        eprintln!("ERROR: could not serialze index into file {index_path:?}: {err}");
+    (model, update)
+}
+
+/// Adds the projects of `index` that are new or modified since they were
+/// added to the search `model`, and removes the ones no longer in `index`
+/// Unchanged projects are not read or lexed again
+pub fn update_search_index(model: &mut Model, index: &Index) -> SearchIndexUpdate {
+    let mut update = SearchIndexUpdate::default();
+
+    let mut indexed: HashSet<PathBuf> = HashSet::new();
+
+    for project in index.projects_map().values() {
+        let modified = document_modified(project);
+
+        let paths: Vec<PathBuf> = std::iter::once(&project.path)
+            .chain(project.members.iter().map(|m| &m.path))
+            .map(PathBuf::from)
+            .collect();
+
+        if paths.iter().any(|p| model.requires_reindexing(p, modified)) {
+            if model.contains(&paths[0]) {
+                update.updated += 1;
+            } else {
+                update.added += 1;
+            }
+
+            add_project_document(model, project);
+        }
+
+        indexed.extend(paths);
+    }
+
+    let removed: Vec<PathBuf> = model
+        .paths()
+        .filter(|path| !indexed.contains(*path))
+        .cloned()
+        .collect();
+
+    for path in removed.iter() {
+        model.remove_document(path);
+    }
+
+    update.removed = removed.len();
+
+    update
+}
+
+/// Latest modification of `project` and its READMEs,
+/// a README edited in place doesn't touch the project directory
+fn document_modified(project: &Project) -> SystemTime {
+    std::iter::once(&project.documentation_file)
+        .chain(project.members.iter().map(|m| &m.documentation_file))
+        .flatten()
+        .filter_map(|file| std::fs::metadata(file).and_then(|m| m.modified()).ok())
+        .fold(SystemTime::from(project.last_modified), SystemTime::max)
+}
+
+/// Add `project`, and its workspace members, to the search `model`
+/// replacing any documents already present for them
+pub fn add_project_document(model: &mut Model, project: &Project) {
+    let modified = document_modified(project);
+
+    let read_doc = |file: &Option<String>| {
+        file.as_ref()
+            .and_then(|f| std::fs::read_to_string(f).ok())
+            .unwrap_or_default()
+    };
+
+    let description = project.description.clone().unwrap_or_default();
+
+    let fields = document_fields(
+        &project.name,
+        &format!("{:?}", project.project_type),
+        &description,
+        read_doc(&project.documentation_file),
+        &project.path,
+    );
+
+    model.add_document(PathBuf::from(&project.path), modified, &fields);
+
+    for member in project.members.iter() {
+        let description = member.description.clone().unwrap_or_default();
+
+        let fields = document_fields(
+            &member.name,
+            &format!("{:?}", member.project_type),
+            &description,
+            read_doc(&member.documentation_file),
+            &member.path,
+        );
+
+        model.add_document(PathBuf::from(&member.path), modified, &fields);
+    }
+}
+
+/// Remove `project` and its workspace members from the search `model`
+pub fn remove_project_document(model: &mut Model, project: &Project) {
+    model.remove_document(Path::new(&project.path));
+
+    for member in project.members.iter() {
+        model.remove_document(Path::new(&member.path));
+    }
+}
+
+/// Saves the search `model` to `search-index.bin` in the config dir
+/// With `LP_EXPORT_SEARCH_INDEX` set, also exports it to `search-index.json`
+pub fn save_model(model: &Model) -> Result<(), ()> {
+    let mut index_path = get_config_dir();
+
+    index_path.push(index_file::SEARCH_INDEX_FILE);
+
+    eprintln!("Saving {index_path:?}...");
+
+    index_file::write(model, &index_path).map_err(|err| {
+        eprintln!("ERROR: could not write index file {index_path:?}: {err}");
     })?;
 
+    if std::env::var_os("LP_EXPORT_SEARCH_INDEX").is_some() {
+        let json_path = index_path.with_file_name("search-index.json");
+
+        index_file::export_json(model, &json_path).map_err(|err| {
+            eprintln!("ERROR: could not export index into file {json_path:?}: {err}");
+        })?;
+    }
+
     Ok(())
 }
+
+/// Fields of a search document for a project,
+/// each field is weighted by the model's `Bm25Params`
+fn document_fields(
+    name: &str,
+    project_type: &str,
+    description: &str,
+    documentation: String,
+    path: &str,
+) -> DocumentFields {
+    DocumentFields {
+        name: name.to_string(),
+        project_type: project_type.to_string(),
+        description: description.to_string(),
+        documentation,
+        path: path.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod search_tests {
+    use std::{
+        path::PathBuf,
+        time::{Duration, SystemTime},
+    };
+
+    use super::{update_search_index, SearchIndexUpdate};
+    use crate::{
+        file_handler::{Project, ProjectType},
+        indexer::Index,
+        search_model::Model,
+    };
+
+    #[test]
+    fn it_should_only_update_changed_projects() {
+        let dir = std::env::temp_dir().join("lp_search_update_test");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let readme = dir.join("README.md");
+        std::fs::write(&readme, "a markdown parser").unwrap();
+
+        let an_hour_ago = SystemTime::now() - Duration::from_secs(3600);
+
+        let mut index = Index::default();
+
+        for name in ["parser", "lexer", "gone"] {
+            let mut project = Project::base(
+                name.to_string(),
+                format!("/p/{name}"),
+                None,
+                ProjectType::Rust,
+            );
+
+            project.last_modified = an_hour_ago.into();
+
+            if name == "parser" {
+                project.documentation_file = Some(readme.to_string_lossy().to_string());
+            }
+
+            index.add_project(&PathBuf::from(&project.path), project);
+        }
+
+        let mut model = Model::default();
+
+        let update = update_search_index(&mut model, &index);
+
+        assert_eq!(update.added, 3);
+        assert!(update_search_index(&mut model, &index).is_empty());
+
+        // newer project dir, and a removed project
+        let mut lexer = index.projects_map()[&PathBuf::from("/p/lexer")].clone();
+        lexer.last_modified = (SystemTime::now() + Duration::from_secs(60)).into();
+
+        index.add_project(&PathBuf::from("/p/lexer"), lexer);
+        index.remove_project(&PathBuf::from("/p/gone"));
+
+        assert_eq!(
+            update_search_index(&mut model, &index),
+            SearchIndexUpdate {
+                added: 0,
+                updated: 1,
+                removed: 1,
+            }
+        );
+
+        assert_eq!(model.len(), 2);
+        assert_eq!(model.doc_frequency("markdown"), 1);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
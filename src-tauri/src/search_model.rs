@@ -1,4 +1,5 @@
 use std::{
+    borrow::Cow,
     collections::HashMap,
     path::{Path, PathBuf},
     time::SystemTime,
@@ -8,103 +9,493 @@ use serde::{Deserialize, Serialize};
 
 use crate::{
     config::get_config_dir,
-    lexer::{self, Lexer},
+    lexer::{Lexer, LexerConfig},
+    timestamp::Timestamp,
 };
 
-pub type TermFreq = HashMap<String, usize>; // frequency for a token
-pub type DocFreq = HashMap<String, usize>; // frequency for a token in all the documents
+mod explain;
+mod fuzzy;
+pub mod index_file;
+
+pub use explain::{Explanation, FieldExplanation, TermExplanation, TokenExplanation};
+pub use fuzzy::TermMatch;
+
+/// Searchable fields of a document
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Field {
+    Name,
+    ProjectType,
+    Description,
+    Documentation,
+    Path,
+}
+
+pub const FIELD_COUNT: usize = 5;
+
+pub const FIELDS: [Field; FIELD_COUNT] = [
+    Field::Name,
+    Field::ProjectType,
+    Field::Description,
+    Field::Documentation,
+    Field::Path,
+];
+
+impl Field {
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// Weight and length normalization of a field
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct FieldParams {
+    /// Multiplier for term frequencies in this field
+    pub weight: f32,
+    /// Length normalization, `0.0` ignores field length,
+    /// `1.0` fully normalizes by it
+    pub b: f32,
+}
+
+/// Tunable parameters of BM25F ranking
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Bm25Params {
+    /// Term frequency saturation
+    pub k1: f32,
+    pub name: FieldParams,
+    pub project_type: FieldParams,
+    pub description: FieldParams,
+    pub documentation: FieldParams,
+    pub path: FieldParams,
+}
+
+impl Default for Bm25Params {
+    fn default() -> Self {
+        Self {
+            k1: 1.2,
+            name: FieldParams {
+                weight: 6.0,
+                b: 0.3,
+            },
+            project_type: FieldParams {
+                weight: 2.5,
+                b: 0.0,
+            },
+            description: FieldParams {
+                weight: 2.0,
+                b: 0.5,
+            },
+            documentation: FieldParams {
+                weight: 1.0,
+                b: 0.75,
+            },
+            path: FieldParams {
+                weight: 1.0,
+                b: 0.3,
+            },
+        }
+    }
+}
 
-#[derive(Deserialize, Serialize, Debug)]
+impl Bm25Params {
+    pub fn field(&self, field: Field) -> FieldParams {
+        match field {
+            Field::Name => self.name,
+            Field::ProjectType => self.project_type,
+            Field::Description => self.description,
+            Field::Documentation => self.documentation,
+            Field::Path => self.path,
+        }
+    }
+}
+
+pub type DocId = u32;
+
+/// Id of an interned term
+pub type TermId = u32;
+
+/// Occurrences of a term in one document, per field
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+pub struct Posting {
+    pub doc: DocId,
+    pub tf: [u32; FIELD_COUNT],
+}
+
+/// Postings of a term, sorted by `DocId`
+pub type Postings = Vec<Posting>;
+
+/// Postings of a term, either in memory or still encoded in the
+/// index file the model was loaded from
+#[derive(Debug)]
+enum PostingsList {
+    Loaded(Postings),
+    /// `len` postings encoded at `offset` of `Model::mapped`
+    Mapped {
+        offset: usize,
+        len: usize,
+    },
+}
+
+impl PostingsList {
+    fn len(&self) -> usize {
+        match self {
+            PostingsList::Loaded(postings) => postings.len(),
+            PostingsList::Mapped { len, .. } => *len,
+        }
+    }
+}
+
+#[derive(Debug)]
 pub struct Doc {
-    tf: TermFreq,
-    count: usize,
-    // SystemTime is platform dependent
-    // to an index generated on mac  may not be deserialized
-    last_modified: SystemTime,
+    pub path: PathBuf,
+    /// Number of tokens in each field
+    lengths: [u32; FIELD_COUNT],
+    /// Distinct terms of the document, to find its postings on removal
+    terms: Vec<TermId>,
+    last_modified: Timestamp,
+}
+
+/// Text of each field of a document to be indexed
+#[derive(Debug, Default)]
+pub struct DocumentFields {
+    pub name: String,
+    pub project_type: String,
+    pub description: String,
+    pub documentation: String,
+    pub path: String,
+}
+
+impl DocumentFields {
+    pub fn get(&self, field: Field) -> &str {
+        match field {
+            Field::Name => &self.name,
+            Field::ProjectType => &self.project_type,
+            Field::Description => &self.description,
+            Field::Documentation => &self.documentation,
+            Field::Path => &self.path,
+        }
+    }
 }
 
-type Docs = HashMap<PathBuf, Doc>; // token frequency for a file
+/// A document matching a search query
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hit {
+    pub path: PathBuf,
+    pub score: f32,
+    /// Fields the matched terms occur in
+    pub fields: Vec<Field>,
+    /// Terms of the vocabulary the query matched in the document
+    pub terms: Vec<String>,
+}
 
-#[derive(Deserialize, Serialize, Default, Debug)]
+/// Inverted index, term -> postings
+/// A query only touches the postings of its terms
+#[derive(Default, Debug)]
 pub struct Model {
-    pub docs: Docs,
-    pub df: DocFreq,
+    /// Documents by `DocId`, `None` for removed ones
+    docs: Vec<Option<Doc>>,
+    doc_ids: HashMap<PathBuf, DocId>,
+    /// Ids of removed documents, reused by new ones
+    free_ids: Vec<DocId>,
+    /// Interned terms, each term is stored once
+    vocabulary: HashMap<String, TermId>,
+    terms: Vec<String>,
+    /// Unstemmed form of each term, first one seen
+    surfaces: Vec<String>,
+    /// Term ids in lexicographic order of their terms
+    sorted_terms: Vec<TermId>,
+    /// Postings by `TermId`
+    postings: Vec<PostingsList>,
+    /// Sum of the lengths of each field over all documents
+    field_lengths: [u64; FIELD_COUNT],
+    pub params: Bm25Params,
+    /// Token pipeline for documents and queries
+    pub lexer: LexerConfig,
+    /// Index file backing `PostingsList::Mapped`
+    mapped: Option<index_file::IndexBytes>,
 }
 
-/// Returns the TF for a term in a particular document
-pub fn compute_tf(term: &str, doc: &Doc) -> f32 {
-    let b = doc.count as f32;
-    let a = doc.tf.get(term).cloned().unwrap_or(0) as f32;
+/// Returns the length normalized frequency of a term in one field of a document
+/// # Arguments
+///
+/// * `tf` occurrences of the term in the field
+/// * `len` length of the field in the document
+/// * `avg_len` average length of the field over all documents
+/// * `params` weight and normalization of the field
+pub fn compute_field_tf(tf: u32, len: u32, avg_len: f32, params: FieldParams) -> f32 {
+    if tf == 0 {
+        return 0.0;
+    }
+
+    params.weight * tf as f32 / compute_field_norm(len, avg_len, params.b)
+}
 
-    a / b
+/// Length normalization of a field, above `1.0` for longer than average fields
+pub fn compute_field_norm(len: u32, avg_len: f32, b: f32) -> f32 {
+    if avg_len > 0.0 {
+        1.0 - b + b * (len as f32 / avg_len)
+    } else {
+        1.0
+    }
 }
 
-/// Computes IDF for a term
+/// Computes IDF for a term, BM25 flavour which is always positive
 /// # Arguments
 ///
-/// * `term` term for calculate for
 /// * `n_docs` number of total documents in the index
-/// * `df` document frequency hash map (map of number of documents a terms appears in)
-pub fn compute_idf(term: &str, n_docs: usize, df: &DocFreq) -> f32 {
+/// * `df` number of documents the term appears in
+pub fn compute_idf(n_docs: usize, df: usize) -> f32 {
     let n = n_docs as f32;
 
-    let m = df.get(term).cloned().unwrap_or(1) as f32;
+    let m = df as f32;
 
-    (n / m).log10() // smaller values are turned negative due to log
+    (1.0 + (n - m + 0.5) / (m + 0.5)).ln()
 }
 
 impl Model {
+    /// Number of documents in the model
+    pub fn len(&self) -> usize {
+        self.doc_ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.doc_ids.is_empty()
+    }
+
+    pub fn contains(&self, file_path: &Path) -> bool {
+        self.doc_ids.contains_key(file_path)
+    }
+
+    /// Paths of all documents in the model
+    pub fn paths(&self) -> impl Iterator<Item = &PathBuf> {
+        self.doc_ids.keys()
+    }
+
+    /// Number of documents `term` appears in
+    pub fn doc_frequency(&self, term: &str) -> usize {
+        self.vocabulary
+            .get(term)
+            .map(|id| self.postings[*id as usize].len())
+            .unwrap_or(0)
+    }
+
+    /// Postings of `term`, decoded from the index file if not loaded yet
+    fn postings(&self, term: TermId) -> Cow<'_, [Posting]> {
+        match &self.postings[term as usize] {
+            PostingsList::Loaded(postings) => Cow::Borrowed(postings),
+            PostingsList::Mapped { offset, len } => {
+                let bytes = self.mapped.as_deref().unwrap_or_default();
+
+                Cow::Owned(index_file::decode_postings(bytes, *offset, *len))
+            }
+        }
+    }
+
+    /// Postings of `term` for modification, loading them into memory
+    fn postings_mut(&mut self, term: TermId) -> &mut Postings {
+        if let PostingsList::Mapped { .. } = self.postings[term as usize] {
+            let postings = self.postings(term).into_owned();
+
+            self.postings[term as usize] = PostingsList::Loaded(postings);
+        }
+
+        match &mut self.postings[term as usize] {
+            PostingsList::Loaded(postings) => postings,
+            PostingsList::Mapped { .. } => unreachable!(),
+        }
+    }
+
+    /// Id of `term`, interning it if it's new
+    fn intern(&mut self, term: String, surface: String) -> TermId {
+        if let Some(id) = self.vocabulary.get(&term) {
+            return *id;
+        }
+
+        let id = self.terms.len() as TermId;
+
+        let pos = self
+            .sorted_terms
+            .partition_point(|t| self.terms[*t as usize] < term);
+
+        self.sorted_terms.insert(pos, id);
+
+        self.terms.push(term.clone());
+        self.surfaces.push(surface);
+        self.postings.push(PostingsList::Loaded(Vec::new()));
+        self.vocabulary.insert(term, id);
+
+        id
+    }
+
+    /// Average length of `field` over all documents
+    fn avg_field_len(&self, field: Field) -> f32 {
+        if self.is_empty() {
+            return 0.0;
+        }
+
+        self.field_lengths[field.index()] as f32 / self.len() as f32
+    }
+
+    fn doc(&self, id: DocId) -> Option<&Doc> {
+        self.docs.get(id as usize).and_then(|d| d.as_ref())
+    }
+
     /// Remove a file from the model
-    /// and also decrements the model's `document frequency` for
-    /// all the terms accordingly
+    /// and also removes its postings for all the terms accordingly
     pub fn remove_document(&mut self, file_path: &Path) {
-        if let Some(doc) = self.docs.remove(file_path) {
-            for t in doc.tf.keys() {
-                if let Some(f) = self.df.get_mut(t) {
-                    *f -= 1;
-                }
+        let Some(id) = self.doc_ids.remove(file_path) else {
+            return;
+        };
+
+        let Some(doc) = self.docs[id as usize].take() else {
+            return;
+        };
+
+        for field in FIELDS {
+            self.field_lengths[field.index()] -= doc.lengths[field.index()] as u64;
+        }
+
+        // terms stay interned with empty postings,
+        // they are dropped when the model is saved
+        for t in doc.terms.iter() {
+            let postings = self.postings_mut(*t);
+
+            if let Ok(pos) = postings.binary_search_by_key(&id, |p| p.doc) {
+                postings.remove(pos);
             }
         }
+
+        self.free_ids.push(id);
     }
 
     /// A document/file requires reindexing
     /// * If it is already present in the index
     /// * And the file is modified after being indexed
-    pub fn requires_reindexing(&mut self, file_path: &Path, last_modified: SystemTime) -> bool {
-        if let Some(doc) = self.docs.get(file_path) {
-            return doc.last_modified < last_modified;
+    pub fn requires_reindexing(&self, file_path: &Path, last_modified: SystemTime) -> bool {
+        if let Some(doc) = self.doc_ids.get(file_path).and_then(|id| self.doc(*id)) {
+            return doc.last_modified < Timestamp::from(last_modified);
         }
 
         return true;
     }
 
-    /// Search for a term `query` in the model
+    /// BM25F weighted term frequency of one posting
+    /// Field frequencies are weighted and normalized first, then
+    /// saturated once per term, so a term repeated across fields
+    /// doesn't count as many independent matches
+    fn posting_tf(&self, posting: &Posting, doc: &Doc) -> f32 {
+        FIELDS
+            .iter()
+            .map(|field| {
+                compute_field_tf(
+                    posting.tf[field.index()],
+                    doc.lengths[field.index()],
+                    self.avg_field_len(*field),
+                    self.params.field(*field),
+                )
+            })
+            .sum()
+    }
+
+    /// Search for a term `query` in the model, all matches best first
     pub fn search_query(&self, query: &[char]) -> Result<Vec<(PathBuf, f32)>, ()> {
-        let mut result = Vec::new();
+        self.search_top_k(query, usize::MAX)
+    }
 
-        let tokens = Lexer::new(&query).collect::<Vec<_>>();
+    /// Search for a term `query` in the model, best `k` matches first
+    pub fn search_top_k(&self, query: &[char], k: usize) -> Result<Vec<(PathBuf, f32)>, ()> {
+        Ok(self
+            .search_hits(query, k)
+            .into_iter()
+            .map(|hit| (hit.path, hit.score))
+            .collect())
+    }
 
-        println!("Search Query Tokens -> {tokens:?}");
+    /// Search for a term `query` in the model, best `k` matches first,
+    /// with the fields and terms each document matched in
+    /// Query tokens also match terms with typos, and the last token
+    /// terms it is a prefix of, see `expand_query`
+    pub fn search_hits(&self, query: &[char], k: usize) -> Vec<Hit> {
+        let expanded = self.expand_query(query);
 
-        for (path, doc) in &self.docs {
-            let mut rank = 0f32;
+        let mut scores: HashMap<DocId, f32> = HashMap::new();
 
-            for token in &tokens {
-                let stemmed = token;
-                rank +=
-                    compute_tf(&stemmed, doc) * compute_idf(&stemmed, self.docs.len(), &self.df);
+        // fields and terms matched in each document
+        let mut matched: HashMap<DocId, ([bool; FIELD_COUNT], Vec<TermId>)> = HashMap::new();
+
+        for (_, matches) in expanded.iter() {
+            // a document scores once per query token,
+            // by the best of the terms the token matched in it
+            let mut token_scores: HashMap<DocId, f32> = HashMap::new();
+
+            for (term, term_match) in matches.iter() {
+                let postings = self.postings(*term);
+
+                if postings.is_empty() {
+                    continue;
+                }
+
+                let idf = compute_idf(self.len(), postings.len());
+
+                for posting in postings.iter() {
+                    let Some(doc) = self.doc(posting.doc) else {
+                        continue;
+                    };
+
+                    let tf = self.posting_tf(posting, doc);
+
+                    let score = term_match.weight() * idf * tf / (self.params.k1 + tf);
+
+                    let best = token_scores.entry(posting.doc).or_insert(0.0);
+                    *best = best.max(score);
+
+                    let (fields, terms) = matched.entry(posting.doc).or_default();
+
+                    for field in FIELDS {
+                        fields[field.index()] |= posting.tf[field.index()] > 0;
+                    }
+
+                    terms.push(*term);
+                }
             }
 
-            if !rank.is_nan() && rank != 0.0 {
-                result.push((path.clone(), rank));
+            for (doc, score) in token_scores {
+                *scores.entry(doc).or_insert(0.0) += score;
             }
         }
 
-        result.sort_by(|(_, sum_tf), (_, sum_tf_2)| sum_tf.partial_cmp(sum_tf_2).unwrap());
+        let mut result: Vec<(DocId, f32)> = scores
+            .into_iter()
+            .filter(|(_, rank)| !rank.is_nan() && *rank != 0.0)
+            .collect();
 
-        result.reverse();
+        let by_rank = |a: &(DocId, f32), b: &(DocId, f32)| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0));
 
-        Ok(result)
+        // only the best `k` need to be sorted
+        if k < result.len() {
+            result.select_nth_unstable_by(k, by_rank);
+            result.truncate(k);
+        }
+
+        result.sort_unstable_by(by_rank);
+
+        result
+            .into_iter()
+            .filter_map(|(id, score)| {
+                let doc = self.doc(id)?;
+
+                let (fields, terms) = matched.remove(&id).unwrap_or_default();
+
+                Some(Hit {
+                    path: doc.path.clone(),
+                    score,
+                    fields: FIELDS.into_iter().filter(|f| fields[f.index()]).collect(),
+                    terms: terms
+                        .into_iter()
+                        .map(|t| self.terms[t as usize].clone())
+                        .collect(),
+                })
+            })
+            .collect()
     }
 
     /// Add a [file]/[document] to the model
@@ -112,60 +503,151 @@ impl Model {
         &mut self,
         file_path: PathBuf,
         last_modified: SystemTime,
-        content: &[char],
+        fields: &DocumentFields,
     ) {
         // if document is already present, removes the model
         self.remove_document(&file_path);
 
-        let mut tf = TermFreq::new();
+        let id = match self.free_ids.pop() {
+            Some(id) => id,
+            None => {
+                self.docs.push(None);
+                (self.docs.len() - 1) as DocId
+            }
+        };
+
+        let mut tf: HashMap<String, ([u32; FIELD_COUNT], String)> = HashMap::new();
+        let mut lengths = [0u32; FIELD_COUNT];
+
+        for field in FIELDS {
+            let content: Vec<char> = fields.get(field).chars().collect();
 
-        let mut count = 0;
+            let mut lexer = Lexer::with_config(&content, &self.lexer);
 
-        for t in lexer::Lexer::new(content) {
-            if let Some(f) = tf.get_mut(&t) {
-                *f += 1;
-            } else {
-                tf.insert(t, 1);
+            while let Some(t) = lexer.next() {
+                let (freqs, _) = tf
+                    .entry(t)
+                    .or_insert_with(|| ([0; FIELD_COUNT], lexer.surface().to_string()));
+
+                freqs[field.index()] += 1;
+
+                lengths[field.index()] += 1;
             }
 
-            count += 1;
+            self.field_lengths[field.index()] += lengths[field.index()] as u64;
         }
 
-        for t in tf.keys() {
-            if let Some(f) = self.df.get_mut(t) {
-                *f += 1;
-            } else {
-                self.df.insert(t.to_string(), 1);
+        let mut terms = Vec::with_capacity(tf.len());
+
+        for (t, (freqs, surface)) in tf {
+            let term = self.intern(t, surface);
+
+            let postings = self.postings_mut(term);
+
+            let posting = Posting { doc: id, tf: freqs };
+
+            // ids are reused, keep postings sorted
+            match postings.binary_search_by_key(&id, |p| p.doc) {
+                Ok(pos) => postings[pos] = posting,
+                Err(pos) => postings.insert(pos, posting),
             }
+
+            terms.push(term);
         }
 
-        self.docs.insert(
-            file_path,
-            Doc {
-                tf,
-                count,
-                last_modified,
-            },
-        );
+        self.docs[id as usize] = Some(Doc {
+            path: file_path.clone(),
+            lengths,
+            terms,
+            last_modified: Timestamp::from(last_modified),
+        });
+
+        self.doc_ids.insert(file_path, id);
     }
 }
 
 /// Loads search model from index file
 pub fn load_model() -> Result<Model, ()> {
     let mut config = get_config_dir();
-    config.push("search-index.json");
+    config.push(index_file::SEARCH_INDEX_FILE);
     if config.exists() {
-        let index_file = std::fs::File::open(&config).map_err(|err| {
-            eprintln!("ERROR: could not open {config:?}: {err}");
-        })?;
-
-        let model_data: Model = serde_json::from_reader(index_file).map_err(|err| {
-            eprintln!("ERROR: could not parse search index file {config:?}: {err}")
-        })?;
-
-        Ok(model_data)
+        index_file::read(&config).map_err(|err| {
+            eprintln!("ERROR: could not load search index file {config:?}: {err}");
+        })
     } else {
         eprintln!("Cannot File search index at {config:?}");
         return Err(());
     }
 }
+
+#[cfg(test)]
+mod search_model_tests {
+    use std::{path::PathBuf, time::SystemTime};
+
+    use super::{compute_idf, DocumentFields, Model};
+
+    fn fields(name: &str, documentation: &str) -> DocumentFields {
+        DocumentFields {
+            name: name.to_string(),
+            project_type: String::from("Rust"),
+            documentation: documentation.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn it_should_rank_short_name_match_above_long_readme_mention() {
+        let mut model = Model::default();
+
+        let readme = "a long readme that talks about many things ".repeat(50) + "parser";
+
+        model.add_document(
+            PathBuf::from("/p/notes"),
+            SystemTime::now(),
+            &fields("notes", &readme),
+        );
+        model.add_document(
+            PathBuf::from("/p/parser"),
+            SystemTime::now(),
+            &fields("parser", "parses things"),
+        );
+        model.add_document(
+            PathBuf::from("/p/other"),
+            SystemTime::now(),
+            &fields("other", "nothing here"),
+        );
+
+        let query: Vec<char> = "parser".chars().collect();
+
+        let result = model.search_query(&query).unwrap();
+
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[0].0, PathBuf::from("/p/parser"));
+
+        // `rust` is in every document, still ranks them
+        let query: Vec<char> = "rust".chars().collect();
+
+        assert_eq!(model.search_query(&query).unwrap().len(), 3);
+
+        model.remove_document(&PathBuf::from("/p/parser"));
+
+        assert_eq!(model.doc_frequency("rust"), 2);
+        assert_eq!(model.doc_frequency("parser"), 1);
+
+        // removed id is reused and postings stay sorted
+        model.add_document(
+            PathBuf::from("/p/parser2"),
+            SystemTime::now(),
+            &fields("parser", ""),
+        );
+
+        assert_eq!(model.len(), 3);
+        assert_eq!(model.search_top_k(&query, 2).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn it_should_never_compute_negative_idf() {
+        assert!(compute_idf(10, 10) > 0.0);
+        assert!(compute_idf(10, 1) > compute_idf(10, 10));
+    }
+}
@@ -0,0 +1,193 @@
+// Breakdown of the score of a document for a query, to tune ranking
+
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use super::{compute_field_norm, compute_field_tf, compute_idf, Field, Model, TermMatch, FIELDS};
+
+/// Why a document scores what it does for a query
+/// The model has no recency boosts, `last_modified` only decides reindexing
+#[derive(Serialize, Debug, Clone)]
+pub struct Explanation {
+    pub path: PathBuf,
+    /// Sum of the token scores, same as `Model::search_hits` gives
+    pub score: f32,
+    /// Documents in the model, the `n` of the IDF
+    pub n_docs: usize,
+    /// Term frequency saturation of the model
+    pub k1: f32,
+    pub tokens: Vec<TokenExplanation>,
+}
+
+/// A query token and the terms it matched in the document
+#[derive(Serialize, Debug, Clone)]
+pub struct TokenExplanation {
+    pub token: String,
+    /// Best score of `terms`, a token only counts once
+    pub score: f32,
+    pub terms: Vec<TermExplanation>,
+}
+
+/// A vocabulary term a query token expanded to
+#[derive(Serialize, Debug, Clone)]
+pub struct TermExplanation {
+    pub term: String,
+    pub term_match: TermMatch,
+    /// Multiplier of `term_match`, below `1.0` for prefixes and typos
+    pub match_weight: f32,
+    pub doc_frequency: usize,
+    pub idf: f32,
+    /// Weighted and normalized term frequency summed over `fields`
+    pub tf: f32,
+    /// `match_weight * idf * tf / (k1 + tf)`
+    pub score: f32,
+    pub fields: Vec<FieldExplanation>,
+}
+
+/// Contribution of one field to the term frequency of a term
+#[derive(Serialize, Debug, Clone)]
+pub struct FieldExplanation {
+    pub field: Field,
+    /// Occurrences of the term in the field
+    pub tf: u32,
+    /// Tokens in the field of the document
+    pub len: u32,
+    /// Average tokens in the field over all documents
+    pub avg_len: f32,
+    pub weight: f32,
+    pub b: f32,
+    /// Length normalization, `tf` is divided by it
+    pub norm: f32,
+    /// `weight * tf / norm`
+    pub weighted_tf: f32,
+}
+
+impl Model {
+    /// Breakdown of the score of the document at `path` for `query`,
+    /// `None` if the document is not in the model
+    /// Tokens that match nothing in the document are kept with no terms
+    pub fn explain(&self, query: &[char], path: &Path) -> Option<Explanation> {
+        let id = *self.doc_ids.get(path)?;
+        let doc = self.doc(id)?;
+
+        let mut tokens = Vec::new();
+
+        for (token, matches) in self.expand_query(query) {
+            let mut terms = Vec::new();
+
+            for (term, term_match) in matches {
+                let postings = self.postings(term);
+
+                let Ok(pos) = postings.binary_search_by_key(&id, |p| p.doc) else {
+                    continue;
+                };
+
+                let posting = &postings[pos];
+
+                let fields: Vec<FieldExplanation> = FIELDS
+                    .into_iter()
+                    .filter(|field| posting.tf[field.index()] > 0)
+                    .map(|field| {
+                        let params = self.params.field(field);
+                        let tf = posting.tf[field.index()];
+                        let len = doc.lengths[field.index()];
+                        let avg_len = self.avg_field_len(field);
+
+                        FieldExplanation {
+                            field,
+                            tf,
+                            len,
+                            avg_len,
+                            weight: params.weight,
+                            b: params.b,
+                            norm: compute_field_norm(len, avg_len, params.b),
+                            weighted_tf: compute_field_tf(tf, len, avg_len, params),
+                        }
+                    })
+                    .collect();
+
+                let idf = compute_idf(self.len(), postings.len());
+                let tf = self.posting_tf(posting, doc);
+
+                terms.push(TermExplanation {
+                    term: self.terms[term as usize].clone(),
+                    term_match,
+                    match_weight: term_match.weight(),
+                    doc_frequency: postings.len(),
+                    idf,
+                    tf,
+                    score: term_match.weight() * idf * tf / (self.params.k1 + tf),
+                    fields,
+                });
+            }
+
+            tokens.push(TokenExplanation {
+                score: terms.iter().map(|t| t.score).fold(0.0, f32::max),
+                token,
+                terms,
+            });
+        }
+
+        Some(Explanation {
+            path: doc.path.clone(),
+            score: tokens.iter().map(|t| t.score).sum(),
+            n_docs: self.len(),
+            k1: self.params.k1,
+            tokens,
+        })
+    }
+}
+
+#[cfg(test)]
+mod explain_tests {
+    use std::{path::PathBuf, time::SystemTime};
+
+    use crate::search_model::{DocumentFields, Field, Model, TermMatch};
+
+    #[test]
+    fn it_should_add_up_to_the_search_score() {
+        let mut model = Model::default();
+
+        for (name, description) in [("tauri-app", "a tauri desktop app"), ("notes", "app")] {
+            model.add_document(
+                PathBuf::from(format!("/p/{name}")),
+                SystemTime::now(),
+                &DocumentFields {
+                    name: name.to_string(),
+                    description: description.to_string(),
+                    ..Default::default()
+                },
+            );
+        }
+
+        let query: Vec<char> = "tauri desk".chars().collect();
+
+        let hits = model.search_hits(&query, usize::MAX);
+
+        let explanation = model
+            .explain(&query, &PathBuf::from("/p/tauri-app"))
+            .unwrap();
+
+        assert_eq!(hits[0].path, explanation.path);
+        assert!((hits[0].score - explanation.score).abs() < 1e-6);
+
+        let tauri = &explanation.tokens[1];
+
+        assert_eq!(tauri.token, "tauri");
+        assert_eq!(tauri.terms[0].term_match, TermMatch::Exact);
+        assert_eq!(
+            tauri.terms[0]
+                .fields
+                .iter()
+                .map(|f| f.field)
+                .collect::<Vec<_>>(),
+            [Field::Name, Field::Description]
+        );
+
+        // `desk` is typed on, a prefix of `desktop`
+        assert_eq!(explanation.tokens[0].terms[0].term_match, TermMatch::Prefix);
+
+        assert!(model.explain(&query, &PathBuf::from("/p/none")).is_none());
+    }
+}
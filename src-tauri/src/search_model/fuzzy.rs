@@ -0,0 +1,291 @@
+// Typo tolerant and prefix matching of query tokens against the vocabulary
+
+use serde::Serialize;
+
+use super::{Model, TermId};
+use crate::lexer::Lexer;
+
+/// Most terms the last query token expands to as a prefix
+const MAX_PREFIX_EXPANSIONS: usize = 30;
+
+/// How a query token matched a vocabulary term
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+pub enum TermMatch {
+    Exact,
+    /// The token is a prefix of the term
+    Prefix,
+    /// The term is this many edits away from the token
+    Fuzzy(u32),
+}
+
+impl TermMatch {
+    /// Multiplier for the score of the matched term,
+    /// a prefix or fuzzy match scores below an exact one
+    pub fn weight(self) -> f32 {
+        match self {
+            TermMatch::Exact => 1.0,
+            TermMatch::Prefix => 0.6,
+            TermMatch::Fuzzy(edits) => 0.5 / edits as f32,
+        }
+    }
+}
+
+/// Edits allowed for a token, short tokens have to match exactly
+fn max_edits(token: &str) -> u32 {
+    match token.chars().count() {
+        0..=2 => 0,
+        3..=5 => 1,
+        _ => 2,
+    }
+}
+
+impl Model {
+    /// Lexes `query` and expands each distinct token to the terms it matches
+    /// The last token is a prefix too, unless the query ends with whitespace
+    pub(super) fn expand_query(&self, query: &[char]) -> Vec<(String, Vec<(TermId, TermMatch)>)> {
+        let mut tokens = Lexer::with_config(query, &self.lexer).collect::<Vec<_>>();
+
+        let typing = query.last().is_some_and(|c| !c.is_whitespace());
+
+        let last = tokens.last().filter(|_| typing).cloned();
+
+        tokens.sort();
+        tokens.dedup();
+
+        tokens
+            .into_iter()
+            .map(|token| {
+                let matches = self.expand_token(&token, last.as_ref() == Some(&token));
+
+                (token, matches)
+            })
+            .collect()
+    }
+
+    fn expand_token(&self, token: &str, as_prefix: bool) -> Vec<(TermId, TermMatch)> {
+        let mut matches = Vec::new();
+
+        if let Some(id) = self.vocabulary.get(token) {
+            if self.postings[*id as usize].len() > 0 {
+                matches.push((*id, TermMatch::Exact));
+            }
+        }
+
+        if as_prefix {
+            for id in self.prefix_terms(token) {
+                if !matches.iter().any(|(m, _)| *m == id) {
+                    matches.push((id, TermMatch::Prefix));
+                }
+            }
+        }
+
+        for (id, edits) in self.fuzzy_terms(token, max_edits(token)) {
+            if edits > 0 && !matches.iter().any(|(m, _)| *m == id) {
+                matches.push((id, TermMatch::Fuzzy(edits)));
+            }
+        }
+
+        matches
+    }
+
+    /// Terms starting with `prefix`, most frequent first
+    fn prefix_terms(&self, prefix: &str) -> Vec<TermId> {
+        let start = self
+            .sorted_terms
+            .partition_point(|id| self.terms[*id as usize].as_str() < prefix);
+
+        let mut ids: Vec<TermId> = self.sorted_terms[start..]
+            .iter()
+            .take_while(|id| self.terms[**id as usize].starts_with(prefix))
+            .filter(|id| self.postings[**id as usize].len() > 0)
+            .copied()
+            .collect();
+
+        ids.sort_by_key(|id| std::cmp::Reverse(self.postings[*id as usize].len()));
+        ids.truncate(MAX_PREFIX_EXPANSIONS);
+
+        ids
+    }
+
+    /// Terms within `max_edits` of `token`, with their distance,
+    /// counting insertions, deletions, substitutions and transpositions
+    /// The sorted vocabulary is walked like a trie, terms sharing a prefix
+    /// share its rows of the distance table, and all terms under a prefix
+    /// that is already too far from `token` are skipped
+    fn fuzzy_terms(&self, token: &str, max_edits: u32) -> Vec<(TermId, u32)> {
+        let query: Vec<char> = token.chars().collect();
+        let width = query.len() + 1;
+
+        // rows[d] holds the distances after `d` chars of `prefix`
+        let mut rows: Vec<Vec<u32>> = vec![(0..width as u32).collect()];
+        let mut prefix: Vec<char> = Vec::new();
+
+        let mut result = Vec::new();
+        let mut i = 0;
+
+        while i < self.sorted_terms.len() {
+            let id = self.sorted_terms[i];
+            let term: Vec<char> = self.terms[id as usize].chars().collect();
+
+            let shared = prefix
+                .iter()
+                .zip(term.iter())
+                .take_while(|(a, b)| a == b)
+                .count();
+
+            rows.truncate(shared + 1);
+            prefix.truncate(shared);
+
+            let mut too_far = false;
+
+            for d in shared..term.len() {
+                let c = term[d];
+
+                let mut row = vec![0; width];
+                row[0] = d as u32 + 1;
+
+                for j in 1..width {
+                    let cost = if query[j - 1] == c { 0 } else { 1 };
+
+                    row[j] = (rows[d][j] + 1)
+                        .min(row[j - 1] + 1)
+                        .min(rows[d][j - 1] + cost);
+
+                    if d > 0 && j > 1 && query[j - 1] == term[d - 1] && query[j - 2] == c {
+                        row[j] = row[j].min(rows[d - 1][j - 2] + 1);
+                    }
+                }
+
+                too_far = row.iter().min().is_some_and(|min| *min > max_edits);
+
+                rows.push(row);
+                prefix.push(c);
+
+                if too_far {
+                    break;
+                }
+            }
+
+            if too_far {
+                let dead: String = prefix.iter().collect();
+
+                i += self.sorted_terms[i..]
+                    .partition_point(|id| self.terms[*id as usize].starts_with(&dead));
+
+                continue;
+            }
+
+            let edits = rows[term.len()][width - 1];
+
+            if edits <= max_edits && self.postings[id as usize].len() > 0 {
+                result.push((id, edits));
+            }
+
+            i += 1;
+        }
+
+        result
+    }
+
+    /// `query` with every unknown word replaced by the closest known one,
+    /// `None` if all words are known or nothing close is found
+    /// A last word that is a prefix of known terms counts as known
+    pub fn did_you_mean(&self, query: &[char]) -> Option<String> {
+        let typing = query.last().is_some_and(|c| !c.is_whitespace());
+
+        let mut lexer = Lexer::with_config(query, &self.lexer);
+
+        let mut words = Vec::new();
+        let mut changed = false;
+
+        let mut next = lexer.next();
+
+        while let Some(token) = next {
+            let surface = lexer.surface().to_string();
+
+            next = lexer.next();
+
+            let is_last = next.is_none();
+
+            let known = self.doc_frequency(&token) > 0
+                || (is_last && typing && !self.prefix_terms(&token).is_empty());
+
+            let closest = (!known)
+                .then(|| {
+                    self.fuzzy_terms(&token, max_edits(&token))
+                        .into_iter()
+                        .min_by_key(|(id, edits)| {
+                            (*edits, std::cmp::Reverse(self.postings[*id as usize].len()))
+                        })
+                })
+                .flatten();
+
+            match closest {
+                Some((id, _)) => {
+                    words.push(self.surfaces[id as usize].clone());
+                    changed = true;
+                }
+                None => words.push(surface),
+            }
+        }
+
+        changed.then(|| words.join(" "))
+    }
+}
+
+#[cfg(test)]
+mod fuzzy_tests {
+    use std::{path::PathBuf, time::SystemTime};
+
+    use crate::search_model::{DocumentFields, Model};
+
+    fn add(model: &mut Model, name: &str) {
+        model.add_document(
+            PathBuf::from(format!("/p/{name}")),
+            SystemTime::now(),
+            &DocumentFields {
+                name: name.to_string(),
+                ..Default::default()
+            },
+        );
+    }
+
+    fn search(model: &Model, query: &str) -> Vec<PathBuf> {
+        let query: Vec<char> = query.chars().collect();
+
+        model
+            .search_query(&query)
+            .unwrap()
+            .into_iter()
+            .map(|(path, _)| path)
+            .collect()
+    }
+
+    #[test]
+    fn it_should_match_typos_and_prefixes_below_exact_matches() {
+        let mut model = Model::default();
+
+        for name in ["tauri-app", "svelte-kit", "taura", "parser"] {
+            add(&mut model, name);
+        }
+
+        assert_eq!(search(&model, "tauir "), [PathBuf::from("/p/tauri-app")]);
+        assert_eq!(search(&model, "svel"), [PathBuf::from("/p/svelte-kit")]);
+
+        // exact hit first, then the one edit away
+        assert_eq!(
+            search(&model, "taura "),
+            [PathBuf::from("/p/taura"), PathBuf::from("/p/tauri-app")]
+        );
+
+        let did_you_mean = |query: &str| {
+            let query: Vec<char> = query.chars().collect();
+            model.did_you_mean(&query)
+        };
+
+        assert_eq!(did_you_mean("tauir app"), Some(String::from("tauri app")));
+        assert_eq!(did_you_mean("parsr"), Some(String::from("parser")));
+        assert_eq!(did_you_mean("tauri"), None);
+        assert_eq!(did_you_mean("svel"), None);
+    }
+}
@@ -0,0 +1,627 @@
+// Binary format of the search index
+//
+// header    magic "LPSI" | version u32 | checksum u32 | body length u64
+// body      field lengths | lexer config | vocabulary | docs | free ids | postings
+//
+// Header integers are little endian, body integers are LEB128 varints
+// * the lexer config the terms were made with is stored as JSON, a model
+//   lexed differently than configured has to be rebuilt
+// * the vocabulary is sorted and front coded, each term is stored as the
+//   length of the prefix shared with the previous term and the rest of it,
+//   followed by its unstemmed surface form, coded against the term
+// * docs and postings refer to terms by their position in the vocabulary
+// * the postings of each term are prefixed by their count and byte length,
+//   so loading skips over them and they are decoded on first use
+// * doc ids within postings are delta encoded
+// * doc modification times are milliseconds since the unix epoch
+
+use std::{
+    collections::BTreeMap,
+    fmt::Debug,
+    fs::File,
+    io::{BufWriter, Read, Write},
+    ops::Deref,
+    path::{Path, PathBuf},
+};
+
+use memmap2::Mmap;
+use serde::Serialize;
+
+use super::{Doc, DocId, Model, Posting, Postings, PostingsList, TermId, FIELDS, FIELD_COUNT};
+use crate::{errors::lp_error::LpError, timestamp::Timestamp};
+
+pub const SEARCH_INDEX_FILE: &str = "search-index.bin";
+
+const MAGIC: &[u8; 4] = b"LPSI";
+
+/// Bumped on every incompatible change of the format, a file of any other
+/// version is not read and the model is rebuilt
+pub const FORMAT_VERSION: u32 = 1;
+
+const HEADER_LEN: usize = 20;
+
+/// Contents of a loaded index file, memory mapped where possible
+pub struct IndexBytes(Box<dyn Deref<Target = [u8]> + Send + Sync>);
+
+impl Deref for IndexBytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl Debug for IndexBytes {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "IndexBytes({} bytes)", self.len())
+    }
+}
+
+/// Writes `model` to `path`
+/// The file is written next to `path` and renamed over it, so a mapped
+/// index file is never modified in place
+pub fn write(model: &Model, path: &Path) -> Result<(), LpError> {
+    let body = encode_body(model);
+
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+    let tmp_path = PathBuf::from(tmp_path);
+
+    {
+        let mut writer = BufWriter::new(File::create(&tmp_path)?);
+
+        writer.write_all(MAGIC)?;
+        writer.write_all(&FORMAT_VERSION.to_le_bytes())?;
+        writer.write_all(&crc32fast::hash(&body).to_le_bytes())?;
+        writer.write_all(&(body.len() as u64).to_le_bytes())?;
+        writer.write_all(&body)?;
+
+        writer
+            .into_inner()
+            .map_err(|err| err.into_error())?
+            .sync_all()?;
+    }
+
+    std::fs::rename(&tmp_path, path)?;
+
+    Ok(())
+}
+
+/// Loads the model in `path`
+/// Docs and vocabulary are read right away, postings stay in the file
+/// until a term is searched for or modified
+pub fn read(path: &Path) -> Result<Model, LpError> {
+    let bytes = map_file(&File::open(path)?)?;
+
+    if bytes.len() < HEADER_LEN || &bytes[0..4] != MAGIC {
+        return Err(corrupt("not a search index file"));
+    }
+
+    let version = u32::from_le_bytes(bytes[4..8].try_into().unwrap());
+
+    if version != FORMAT_VERSION {
+        return Err(LpError::Error(format!(
+            "unsupported search index version {version}, expected {FORMAT_VERSION}"
+        )));
+    }
+
+    let checksum = u32::from_le_bytes(bytes[8..12].try_into().unwrap());
+    let body_len = u64::from_le_bytes(bytes[12..20].try_into().unwrap());
+
+    if (bytes.len() - HEADER_LEN) as u64 != body_len {
+        return Err(corrupt("truncated file"));
+    }
+
+    if crc32fast::hash(&bytes[HEADER_LEN..]) != checksum {
+        return Err(corrupt("checksum mismatch"));
+    }
+
+    let mut model = Model::default();
+
+    let mut reader = Reader {
+        bytes: &bytes,
+        pos: HEADER_LEN,
+    };
+
+    for field in FIELDS {
+        model.field_lengths[field.index()] = reader.varint()?;
+    }
+
+    let lexer_len = reader.varint()? as usize;
+
+    model.lexer = serde_json::from_slice(reader.take(lexer_len)?).map_err(LpError::SerdeError)?;
+
+    let term_count = reader.varint()? as usize;
+
+    let mut prev: Vec<u8> = Vec::new();
+
+    for id in 0..term_count {
+        let shared = reader.varint()? as usize;
+        let suffix_len = reader.varint()? as usize;
+
+        if shared > prev.len() {
+            return Err(corrupt("vocabulary"));
+        }
+
+        prev.truncate(shared);
+        prev.extend_from_slice(reader.take(suffix_len)?);
+
+        let term = String::from_utf8(prev.clone()).map_err(|_| corrupt("vocabulary"))?;
+
+        let surface_shared = reader.varint()? as usize;
+        let surface_suffix_len = reader.varint()? as usize;
+
+        if surface_shared > prev.len() {
+            return Err(corrupt("vocabulary"));
+        }
+
+        let mut surface = prev[..surface_shared].to_vec();
+        surface.extend_from_slice(reader.take(surface_suffix_len)?);
+
+        let surface = String::from_utf8(surface).map_err(|_| corrupt("vocabulary"))?;
+
+        model.vocabulary.insert(term.clone(), id as TermId);
+        model.terms.push(term);
+        model.surfaces.push(surface);
+        model.sorted_terms.push(id as TermId);
+    }
+
+    let doc_slots = reader.varint()? as usize;
+
+    for id in 0..doc_slots {
+        if reader.take(1)?[0] == 0 {
+            model.docs.push(None);
+            continue;
+        }
+
+        let path_len = reader.varint()? as usize;
+        let path = std::str::from_utf8(reader.take(path_len)?).map_err(|_| corrupt("doc path"))?;
+        let path = PathBuf::from(path);
+
+        let mut lengths = [0u32; FIELD_COUNT];
+
+        for length in lengths.iter_mut() {
+            *length = reader.varint()? as u32;
+        }
+
+        let last_modified = Timestamp::from_millis(reader.varint()? as i64);
+
+        let n_terms = reader.varint()? as usize;
+
+        let mut terms = Vec::with_capacity(n_terms);
+        let mut term = 0;
+
+        for _ in 0..n_terms {
+            term += reader.varint()?;
+
+            if term as usize >= term_count {
+                return Err(corrupt("doc terms"));
+            }
+
+            terms.push(term as TermId);
+        }
+
+        model.doc_ids.insert(path.clone(), id as DocId);
+
+        model.docs.push(Some(Doc {
+            path,
+            lengths,
+            terms,
+            last_modified,
+        }));
+    }
+
+    let free_count = reader.varint()? as usize;
+
+    for _ in 0..free_count {
+        model.free_ids.push(reader.varint()? as DocId);
+    }
+
+    for _ in 0..term_count {
+        let len = reader.varint()? as usize;
+        let byte_len = reader.varint()? as usize;
+
+        let offset = reader.pos;
+
+        reader.take(byte_len)?;
+
+        model.postings.push(PostingsList::Mapped { offset, len });
+    }
+
+    model.mapped = Some(bytes);
+
+    Ok(model)
+}
+
+/// Decodes `len` postings encoded at `offset` of `bytes`
+/// `bytes` is checked on load, a malformed list ends early
+pub fn decode_postings(bytes: &[u8], offset: usize, len: usize) -> Postings {
+    let mut reader = Reader { bytes, pos: offset };
+
+    let mut postings = Vec::with_capacity(len);
+    let mut doc: u64 = 0;
+
+    let mut next = |doc: &mut u64| -> Result<Posting, LpError> {
+        *doc += reader.varint()?;
+
+        let mut tf = [0u32; FIELD_COUNT];
+
+        for freq in tf.iter_mut() {
+            *freq = reader.varint()? as u32;
+        }
+
+        Ok(Posting {
+            doc: *doc as DocId,
+            tf,
+        })
+    };
+
+    for _ in 0..len {
+        match next(&mut doc) {
+            Ok(posting) => postings.push(posting),
+            Err(err) => {
+                eprintln!("ERROR: decoding postings : {err}");
+                break;
+            }
+        }
+    }
+
+    postings
+}
+
+/// Document of the JSON export
+#[derive(Serialize)]
+struct JsonDoc<'a> {
+    id: DocId,
+    path: &'a Path,
+    lengths: [u32; FIELD_COUNT],
+    last_modified: Timestamp,
+}
+
+/// Posting of the JSON export, by path rather than `DocId`
+#[derive(Serialize)]
+struct JsonPosting<'a> {
+    path: &'a Path,
+    tf: [u32; FIELD_COUNT],
+}
+
+/// Human readable dump of a model, for debugging
+#[derive(Serialize)]
+struct JsonExport<'a> {
+    version: u32,
+    field_lengths: [u64; FIELD_COUNT],
+    docs: Vec<JsonDoc<'a>>,
+    postings: BTreeMap<&'a str, Vec<JsonPosting<'a>>>,
+}
+
+/// Writes a JSON export of `model` to `path`
+/// It is for inspecting an index only, it is never loaded back
+pub fn export_json(model: &Model, path: &Path) -> Result<(), LpError> {
+    let docs = model
+        .docs
+        .iter()
+        .enumerate()
+        .filter_map(|(id, doc)| {
+            doc.as_ref().map(|doc| JsonDoc {
+                id: id as DocId,
+                path: &doc.path,
+                lengths: doc.lengths,
+                last_modified: doc.last_modified,
+            })
+        })
+        .collect();
+
+    let mut postings = BTreeMap::new();
+
+    for (id, term) in model.terms.iter().enumerate() {
+        let list: Vec<JsonPosting> = model
+            .postings(id as TermId)
+            .iter()
+            .filter_map(|p| {
+                model.doc(p.doc).map(|doc| JsonPosting {
+                    path: &doc.path,
+                    tf: p.tf,
+                })
+            })
+            .collect();
+
+        if !list.is_empty() {
+            postings.insert(term.as_str(), list);
+        }
+    }
+
+    let export = JsonExport {
+        version: FORMAT_VERSION,
+        field_lengths: model.field_lengths,
+        docs,
+        postings,
+    };
+
+    let file = File::create(path)?;
+
+    serde_json::to_writer_pretty(BufWriter::new(file), &export).map_err(LpError::SerdeError)?;
+
+    Ok(())
+}
+
+/// Encodes everything after the header
+/// Terms without postings are dropped and the rest renumbered in sorted order
+fn encode_body(model: &Model) -> Vec<u8> {
+    let mut out = Vec::new();
+
+    for field in FIELDS {
+        put_varint(&mut out, model.field_lengths[field.index()]);
+    }
+
+    let lexer = serde_json::to_vec(&model.lexer).unwrap_or_default();
+
+    put_varint(&mut out, lexer.len() as u64);
+    out.extend_from_slice(&lexer);
+
+    let mut live: Vec<(TermId, &str)> = model
+        .terms
+        .iter()
+        .enumerate()
+        .filter(|(id, _)| model.postings[*id].len() > 0)
+        .map(|(id, term)| (id as TermId, term.as_str()))
+        .collect();
+
+    live.sort_unstable_by(|a, b| a.1.cmp(b.1));
+
+    let mut new_ids = vec![TermId::MAX; model.terms.len()];
+
+    for (new_id, (old_id, _)) in live.iter().enumerate() {
+        new_ids[*old_id as usize] = new_id as TermId;
+    }
+
+    put_varint(&mut out, live.len() as u64);
+
+    let mut prev: &[u8] = &[];
+
+    for (old_id, term) in live.iter() {
+        let term = term.as_bytes();
+
+        put_front_coded(&mut out, prev, term);
+        put_front_coded(&mut out, term, model.surfaces[*old_id as usize].as_bytes());
+
+        prev = term;
+    }
+
+    put_varint(&mut out, model.docs.len() as u64);
+
+    for doc in model.docs.iter() {
+        let Some(doc) = doc else {
+            out.push(0);
+            continue;
+        };
+
+        out.push(1);
+
+        let path = doc.path.to_string_lossy();
+
+        put_varint(&mut out, path.len() as u64);
+        out.extend_from_slice(path.as_bytes());
+
+        for length in doc.lengths {
+            put_varint(&mut out, length as u64);
+        }
+
+        // documents modified before the epoch are clamped to it
+        put_varint(&mut out, doc.last_modified.as_millis().max(0) as u64);
+
+        let mut terms: Vec<TermId> = doc
+            .terms
+            .iter()
+            .map(|t| new_ids[*t as usize])
+            .filter(|t| *t != TermId::MAX)
+            .collect();
+
+        terms.sort_unstable();
+
+        put_varint(&mut out, terms.len() as u64);
+
+        let mut prev = 0;
+
+        for term in terms {
+            put_varint(&mut out, (term - prev) as u64);
+            prev = term;
+        }
+    }
+
+    put_varint(&mut out, model.free_ids.len() as u64);
+
+    for id in model.free_ids.iter() {
+        put_varint(&mut out, *id as u64);
+    }
+
+    let mut encoded = Vec::new();
+
+    for (old_id, _) in live.iter() {
+        let postings = model.postings(*old_id);
+
+        encoded.clear();
+
+        let mut prev = 0;
+
+        for posting in postings.iter() {
+            put_varint(&mut encoded, (posting.doc - prev) as u64);
+            prev = posting.doc;
+
+            for freq in posting.tf {
+                put_varint(&mut encoded, freq as u64);
+            }
+        }
+
+        put_varint(&mut out, postings.len() as u64);
+        put_varint(&mut out, encoded.len() as u64);
+        out.extend_from_slice(&encoded);
+    }
+
+    out
+}
+
+fn map_file(file: &File) -> Result<IndexBytes, LpError> {
+    // Windows can't rename over a mapped file, which `write` relies on
+    if cfg!(windows) {
+        let mut bytes = Vec::new();
+        (&*file).read_to_end(&mut bytes)?;
+
+        return Ok(IndexBytes(Box::new(bytes)));
+    }
+
+    // SAFETY: index files are only ever replaced by `write` through a
+    // rename, the mapped file itself is never modified
+    let mmap = unsafe { Mmap::map(file)? };
+
+    Ok(IndexBytes(Box::new(mmap)))
+}
+
+/// Writes `bytes` as the length of the prefix shared with `prev`
+/// and the rest of it
+fn put_front_coded(out: &mut Vec<u8>, prev: &[u8], bytes: &[u8]) {
+    let shared = prev
+        .iter()
+        .zip(bytes.iter())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    put_varint(out, shared as u64);
+    put_varint(out, (bytes.len() - shared) as u64);
+    out.extend_from_slice(&bytes[shared..]);
+}
+
+fn corrupt(what: &str) -> LpError {
+    LpError::Error(format!("corrupt search index: {what}"))
+}
+
+fn put_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+
+    out.push(value as u8);
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], LpError> {
+        let end = self
+            .pos
+            .checked_add(n)
+            .filter(|end| *end <= self.bytes.len())
+            .ok_or_else(|| corrupt("unexpected end of file"))?;
+
+        let slice = &self.bytes[self.pos..end];
+        self.pos = end;
+
+        Ok(slice)
+    }
+
+    fn varint(&mut self) -> Result<u64, LpError> {
+        let mut value = 0u64;
+
+        for shift in (0..64).step_by(7) {
+            let byte = self.take(1)?[0];
+
+            value |= ((byte & 0x7f) as u64) << shift;
+
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+
+        Err(corrupt("varint too long"))
+    }
+}
+
+#[cfg(test)]
+mod index_file_tests {
+    use std::{path::PathBuf, time::SystemTime};
+
+    use super::{read, write};
+    use crate::search_model::{DocumentFields, Model};
+
+    fn fields(name: &str, description: &str) -> DocumentFields {
+        DocumentFields {
+            name: name.to_string(),
+            project_type: String::from("Rust"),
+            description: description.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn it_should_round_trip_model_through_binary_file() {
+        let path = std::env::temp_dir().join("lp_index_file_test.bin");
+
+        let mut model = Model::default();
+
+        model.lexer.stemming = false;
+
+        model.add_document(
+            PathBuf::from("/p/parser"),
+            SystemTime::now(),
+            &fields("parser", "parses things"),
+        );
+        model.add_document(
+            PathBuf::from("/p/lexer"),
+            SystemTime::now(),
+            &fields("lexer", "lexes things for the parser"),
+        );
+        model.add_document(
+            PathBuf::from("/p/removed"),
+            SystemTime::now(),
+            &fields("removed", "zebra"),
+        );
+        model.remove_document(&PathBuf::from("/p/removed"));
+
+        write(&model, &path).unwrap();
+
+        let mut loaded = read(&path).unwrap();
+
+        assert_eq!(loaded.len(), 2);
+        assert_eq!(loaded.lexer, model.lexer);
+        assert_eq!(loaded.doc_frequency("things"), 2);
+        assert_eq!(loaded.doc_frequency("zebra"), 0);
+
+        let query: Vec<char> = "parser things".chars().collect();
+
+        assert_eq!(
+            loaded.search_query(&query).unwrap(),
+            model.search_query(&query).unwrap()
+        );
+
+        // modifying a mapped model, then saving over its own file
+        loaded.remove_document(&PathBuf::from("/p/parser"));
+        loaded.add_document(
+            PathBuf::from("/p/tokens"),
+            SystemTime::now(),
+            &fields("tokens", "things"),
+        );
+
+        write(&loaded, &path).unwrap();
+
+        let reloaded = read(&path).unwrap();
+
+        assert_eq!(reloaded.len(), 2);
+        assert_eq!(reloaded.doc_frequency("things"), 2);
+        assert_eq!(reloaded.doc_frequency("lexes"), 1);
+
+        drop(reloaded);
+
+        // a flipped byte fails the checksum
+        let mut bytes = std::fs::read(&path).unwrap();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0xff;
+        std::fs::write(&path, bytes).unwrap();
+
+        assert!(read(&path).is_err());
+
+        let _ = std::fs::remove_file(&path);
+    }
+}
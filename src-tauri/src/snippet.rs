@@ -0,0 +1,170 @@
+// Snippets of a project's README or description around matched terms
+
+use std::{cmp::Reverse, collections::HashSet, ops::Range};
+
+use serde::Serialize;
+
+use crate::{
+    lexer::{Lexer, LexerConfig},
+    search_model::Field,
+};
+
+/// Longest snippet, in chars of the content
+const SNIPPET_CHARS: usize = 200;
+
+/// Chars kept before the first match of a snippet
+const CONTEXT_CHARS: usize = 40;
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Snippet {
+    /// Field the snippet is taken from
+    pub field: Field,
+    pub text: String,
+    /// Char ranges of the matched terms in `text`
+    pub highlights: Vec<Range<usize>>,
+}
+
+/// Snippet of `content` around the most matches of `terms`,
+/// `None` if none of them occurs in it
+/// `terms` are lexed terms, `content` is lexed with `config` to find them
+/// Whitespace runs are collapsed and cut off text is marked with `…`
+pub fn snippet(
+    field: Field,
+    content: &str,
+    terms: &HashSet<String>,
+    config: &LexerConfig,
+) -> Option<Snippet> {
+    let chars: Vec<char> = content.chars().collect();
+
+    let mut matches: Vec<Range<usize>> = Vec::new();
+
+    let mut lexer = Lexer::with_config(&chars, config);
+
+    while let Some(token) = lexer.next() {
+        if terms.contains(&token) {
+            matches.push(lexer.span());
+        }
+    }
+
+    // the match followed by the most matches within one snippet
+    let best = (0..matches.len()).max_by_key(|i| {
+        let limit = matches[*i].start + SNIPPET_CHARS - CONTEXT_CHARS;
+
+        let count = matches[*i..].iter().take_while(|m| m.end <= limit).count();
+
+        (count, Reverse(*i))
+    })?;
+
+    let first = matches[best].clone();
+
+    // start and end at word boundaries
+    let mut start = first.start.saturating_sub(CONTEXT_CHARS);
+
+    while start > 0 && start < first.start && !chars[start - 1].is_whitespace() {
+        start += 1;
+    }
+
+    let mut end = (start + SNIPPET_CHARS).min(chars.len()).max(first.end);
+
+    while end < chars.len() && end > first.end && !chars[end].is_whitespace() {
+        end -= 1;
+    }
+
+    let mut text = String::new();
+    let mut len = 0;
+
+    // `offsets[i]` is where char `start + i` of `content` is in `text`
+    let mut offsets = vec![0; end - start + 1];
+
+    if start > 0 {
+        text.push_str("… ");
+        len += 2;
+    }
+
+    let mut after_space = true;
+
+    for (i, c) in chars[start..end].iter().enumerate() {
+        offsets[i] = len;
+
+        if !c.is_whitespace() {
+            text.push(*c);
+            len += 1;
+            after_space = false;
+        } else if !after_space {
+            text.push(' ');
+            len += 1;
+            after_space = true;
+        }
+    }
+
+    offsets[end - start] = len;
+
+    if text.ends_with(' ') {
+        text.pop();
+    }
+
+    if end < chars.len() {
+        text.push_str(" …");
+    }
+
+    let highlights = matches
+        .iter()
+        .filter(|m| m.start >= start && m.end <= end)
+        .map(|m| offsets[m.start - start]..offsets[m.end - start])
+        .collect();
+
+    Some(Snippet {
+        field,
+        text,
+        highlights,
+    })
+}
+
+#[cfg(test)]
+mod snippet_tests {
+    use std::collections::HashSet;
+
+    use super::snippet;
+    use crate::{lexer::LexerConfig, search_model::Field};
+
+    #[test]
+    fn it_should_highlight_matched_terms_in_a_window() {
+        let readme = format!(
+            "{}A fast   markdown\nparser written in Rust. {}",
+            "intro words ".repeat(20),
+            "more text ".repeat(30)
+        );
+
+        let terms = HashSet::from([String::from("parser"), String::from("rust")]);
+
+        let found = snippet(
+            Field::Documentation,
+            &readme,
+            &terms,
+            &LexerConfig::default(),
+        )
+        .unwrap();
+
+        assert!(found.text.starts_with("… "));
+        assert!(found.text.ends_with(" …"));
+        assert!(found
+            .text
+            .contains("A fast markdown parser written in Rust."));
+
+        let highlighted: Vec<String> = found
+            .highlights
+            .iter()
+            .map(|h| found.text.chars().skip(h.start).take(h.len()).collect())
+            .collect();
+
+        assert_eq!(highlighted, ["parser", "Rust"]);
+
+        assert!(snippet(
+            Field::Description,
+            "nothing",
+            &terms,
+            &LexerConfig::default()
+        )
+        .is_none());
+    }
+}
@@ -0,0 +1,465 @@
+// Finds tasks in package.json, Cargo.toml, .cargo/config.toml,
+// Makefiles, justfiles, pubspec.yaml and pyproject.toml
+
+use std::{fs, path::Path};
+
+use toml::Table;
+
+use super::{Task, TaskSource};
+
+/// Cargo commands every Cargo project has, `run` is added for binaries
+const CARGO_COMMANDS: [&str; 6] = ["build", "check", "test", "clippy", "fmt", "doc"];
+
+const MAKEFILES: [&str; 3] = ["GNUmakefile", "makefile", "Makefile"];
+
+const JUSTFILES: [&str; 3] = ["justfile", "Justfile", ".justfile"];
+
+const FLUTTER_COMMANDS: [&[&str]; 4] = [&["pub", "get"], &["run"], &["test"], &["analyze"]];
+
+const POETRY_COMMANDS: [&str; 3] = ["install", "update", "build"];
+
+/// Tasks of the project at `dir`
+pub fn discover(dir: &Path) -> Vec<Task> {
+    let mut tasks = Vec::new();
+
+    tasks.extend(npm_tasks(dir));
+    tasks.extend(cargo_tasks(dir));
+    tasks.extend(make_tasks(dir));
+    tasks.extend(just_tasks(dir));
+    tasks.extend(flutter_tasks(dir));
+    tasks.extend(poetry_tasks(dir));
+
+    tasks
+}
+
+fn read_toml(path: &Path) -> Option<Table> {
+    let content = fs::read_to_string(path).ok()?;
+
+    toml::from_str::<Table>(&content)
+        .map_err(|err| eprintln!("ERROR: parsing {path:?} : {err}"))
+        .ok()
+}
+
+/// `scripts` of package.json, run with the package manager of the lock file
+fn npm_tasks(dir: &Path) -> Vec<Task> {
+    let Some(package_json) = fs::read_to_string(dir.join("package.json"))
+        .ok()
+        .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+    else {
+        return Vec::new();
+    };
+
+    let Some(scripts) = package_json.get("scripts").and_then(|s| s.as_object()) else {
+        return Vec::new();
+    };
+
+    let manager = node_package_manager(dir, &package_json);
+
+    scripts
+        .iter()
+        .map(|(name, script)| {
+            Task::new(
+                TaskSource::Npm,
+                name,
+                &[manager, "run", name],
+                script.as_str().map(String::from),
+            )
+        })
+        .collect()
+}
+
+/// `packageManager` of package.json, or the one whose lock file exists
+fn node_package_manager(dir: &Path, package_json: &serde_json::Value) -> &'static str {
+    const MANAGERS: [(&str, &str); 4] = [
+        ("pnpm", "pnpm-lock.yaml"),
+        ("yarn", "yarn.lock"),
+        ("bun", "bun.lockb"),
+        ("bun", "bun.lock"),
+    ];
+
+    let declared = package_json
+        .get("packageManager")
+        .and_then(|m| m.as_str())
+        .and_then(|m| m.split('@').next());
+
+    MANAGERS
+        .iter()
+        .find(|(manager, lock_file)| {
+            declared == Some(*manager) || (declared.is_none() && dir.join(lock_file).is_file())
+        })
+        .map(|(manager, _)| *manager)
+        .unwrap_or("npm")
+}
+
+/// Standard cargo commands and the aliases of `.cargo/config.toml`
+fn cargo_tasks(dir: &Path) -> Vec<Task> {
+    let Some(manifest) = read_toml(&dir.join("Cargo.toml")) else {
+        return Vec::new();
+    };
+
+    let has_binary = manifest.contains_key("bin") || dir.join("src").join("main.rs").is_file();
+
+    let mut tasks: Vec<Task> = CARGO_COMMANDS
+        .iter()
+        .map(|command| Task::new(TaskSource::Cargo, command, &["cargo", command], None))
+        .collect();
+
+    if has_binary {
+        tasks.insert(
+            3,
+            Task::new(TaskSource::Cargo, "run", &["cargo", "run"], None),
+        );
+    }
+
+    // `.cargo/config` is the name used before cargo 1.39
+    let config = [".cargo/config.toml", ".cargo/config"]
+        .iter()
+        .find_map(|name| read_toml(&dir.join(name)));
+
+    let aliases = config
+        .as_ref()
+        .and_then(|config| config.get("alias"))
+        .and_then(|alias| alias.as_table());
+
+    for (name, value) in aliases.into_iter().flatten() {
+        let expansion = match value {
+            toml::Value::String(expansion) => Some(expansion.clone()),
+            toml::Value::Array(args) => Some(
+                args.iter()
+                    .filter_map(|arg| arg.as_str())
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            ),
+            _ => None,
+        };
+
+        tasks.push(Task::new(
+            TaskSource::CargoAlias,
+            name,
+            &["cargo", name],
+            expansion,
+        ));
+    }
+
+    tasks
+}
+
+fn make_tasks(dir: &Path) -> Vec<Task> {
+    let Some(content) = MAKEFILES
+        .iter()
+        .find_map(|name| fs::read_to_string(dir.join(name)).ok())
+    else {
+        return Vec::new();
+    };
+
+    parse_makefile(&content)
+        .into_iter()
+        .map(|(target, description)| {
+            Task::new(TaskSource::Make, &target, &["make", &target], description)
+        })
+        .collect()
+}
+
+/// Explicit targets of a Makefile, with their `## description`
+/// Special targets like `.PHONY`, pattern rules and variables are skipped
+pub(crate) fn parse_makefile(content: &str) -> Vec<(String, Option<String>)> {
+    let mut targets: Vec<(String, Option<String>)> = Vec::new();
+
+    for line in content.lines() {
+        if line.starts_with([' ', '\t', '#', '.']) {
+            continue;
+        }
+
+        let Some((names, rest)) = line.split_once(':') else {
+            continue;
+        };
+
+        // `VAR := value`, `VAR ::= value` or `VAR = a:b`
+        if names.contains('=') || rest.trim_start_matches(':').starts_with('=') {
+            continue;
+        }
+
+        let description = rest
+            .split_once("##")
+            .map(|(_, description)| description.trim().to_string())
+            .filter(|description| !description.is_empty());
+
+        for name in names.split_whitespace() {
+            let valid = !name.starts_with('.')
+                && name
+                    .chars()
+                    .all(|c| c.is_alphanumeric() || "-_./".contains(c));
+
+            if valid && !targets.iter().any(|(target, _)| target == name) {
+                targets.push((name.to_string(), description.clone()));
+            }
+        }
+    }
+
+    targets
+}
+
+fn just_tasks(dir: &Path) -> Vec<Task> {
+    let Some(content) = JUSTFILES
+        .iter()
+        .find_map(|name| fs::read_to_string(dir.join(name)).ok())
+    else {
+        return Vec::new();
+    };
+
+    parse_justfile(&content)
+        .into_iter()
+        .map(|(recipe, description)| {
+            Task::new(TaskSource::Just, &recipe, &["just", &recipe], description)
+        })
+        .collect()
+}
+
+/// Public recipes of a justfile, with the comment above them
+/// Recipes starting with `_` or marked `[private]` are skipped
+pub(crate) fn parse_justfile(content: &str) -> Vec<(String, Option<String>)> {
+    let mut recipes = Vec::new();
+
+    let mut comment = None;
+    let mut private = false;
+
+    for line in content.lines() {
+        if line.trim().is_empty() || line.starts_with([' ', '\t']) {
+            comment = None;
+            private = false;
+            continue;
+        }
+
+        if let Some(text) = line.strip_prefix('#') {
+            if !text.starts_with('!') {
+                comment = Some(text.trim().to_string());
+            }
+            continue;
+        }
+
+        if line.starts_with('[') {
+            private |= line.contains("private");
+            continue;
+        }
+
+        let Some((head, rest)) = line.split_once(':') else {
+            continue;
+        };
+
+        // `name := value`, including `set`, `alias` and `export` lines
+        if rest.starts_with('=') {
+            comment = None;
+            continue;
+        }
+
+        let name = head
+            .split_whitespace()
+            .next()
+            .unwrap_or_default()
+            .trim_start_matches('@');
+
+        let valid = !name.is_empty()
+            && !name.starts_with('_')
+            && name
+                .chars()
+                .all(|c| c.is_alphanumeric() || "-_".contains(c));
+
+        if valid && !private {
+            recipes.push((name.to_string(), comment.take()));
+        }
+
+        comment = None;
+        private = false;
+    }
+
+    recipes
+}
+
+/// Flutter commands, if pubspec.yaml depends on the flutter sdk
+fn flutter_tasks(dir: &Path) -> Vec<Task> {
+    let is_flutter = fs::read_to_string(dir.join("pubspec.yaml"))
+        .ok()
+        .and_then(|content| serde_yaml::from_str::<serde_yaml::Value>(&content).ok())
+        .and_then(|pubspec| pubspec.get("dependencies")?.get("flutter").cloned())
+        .is_some();
+
+    if !is_flutter {
+        return Vec::new();
+    }
+
+    FLUTTER_COMMANDS
+        .iter()
+        .map(|args| {
+            let command: Vec<&str> = std::iter::once("flutter")
+                .chain(args.iter().copied())
+                .collect();
+
+            Task::new(TaskSource::Flutter, &args.join(" "), &command, None)
+        })
+        .collect()
+}
+
+/// Poetry commands and scripts, if the project is managed by poetry
+fn poetry_tasks(dir: &Path) -> Vec<Task> {
+    let pyproject = read_toml(&dir.join("pyproject.toml")).unwrap_or_default();
+
+    let poetry = pyproject.get("tool").and_then(|tool| tool.get("poetry"));
+
+    if poetry.is_none() && !dir.join("poetry.lock").is_file() {
+        return Vec::new();
+    }
+
+    let mut tasks: Vec<Task> = POETRY_COMMANDS
+        .iter()
+        .map(|command| Task::new(TaskSource::Poetry, command, &["poetry", command], None))
+        .collect();
+
+    // `[tool.poetry.scripts]`, or `[project.scripts]` since poetry 2
+    let scripts = [poetry, pyproject.get("project")]
+        .into_iter()
+        .flatten()
+        .filter_map(|table| table.get("scripts")?.as_table())
+        .flatten();
+
+    for (name, entry_point) in scripts {
+        if tasks.iter().any(|task| &task.name == name) {
+            continue;
+        }
+
+        tasks.push(Task::new(
+            TaskSource::Poetry,
+            name,
+            &["poetry", "run", name],
+            entry_point.as_str().map(String::from),
+        ));
+    }
+
+    tasks
+}
+
+#[cfg(test)]
+mod discover_tests {
+    use super::{discover, parse_justfile, parse_makefile};
+    use crate::tasks::TaskSource;
+
+    #[test]
+    fn it_should_parse_makefile_and_justfile_targets() {
+        let makefile = "\
+VERSION := 1.0
+URL = http://example.com
+.PHONY: build test
+
+build: deps ## Build the binary
+\tgo build ./...
+
+test lint:
+\tgo test ./...
+
+%.o: %.c
+\tcc -c $<
+
+$(BIN): build
+";
+
+        assert_eq!(
+            parse_makefile(makefile),
+            vec![
+                (
+                    String::from("build"),
+                    Some(String::from("Build the binary"))
+                ),
+                (String::from("test"), None),
+                (String::from("lint"), None),
+            ]
+        );
+
+        let justfile = "\
+#!/usr/bin/env just --justfile
+set shell := [\"bash\", \"-c\"]
+alias b := build
+
+# Build everything
+build target='all':
+    cargo build
+
+[private]
+setup:
+    ./setup.sh
+
+_helper:
+    echo hidden
+
+@serve port=\":8000\": build
+    ./serve {{port}}
+";
+
+        assert_eq!(
+            parse_justfile(justfile),
+            vec![
+                (
+                    String::from("build"),
+                    Some(String::from("Build everything"))
+                ),
+                (String::from("serve"), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn it_should_discover_npm_cargo_and_poetry_tasks() {
+        let dir = std::env::temp_dir().join("lp_discover_tasks_test");
+        let _ = std::fs::remove_dir_all(&dir);
+
+        std::fs::create_dir_all(dir.join(".cargo")).unwrap();
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+
+        std::fs::write(
+            dir.join("package.json"),
+            r#"{ "scripts": { "dev": "vite" }, "packageManager": "pnpm@8.6.0" }"#,
+        )
+        .unwrap();
+        std::fs::write(dir.join("Cargo.toml"), "[package]\nname = \"lp\"\n").unwrap();
+        std::fs::write(dir.join("src").join("main.rs"), "fn main() {}").unwrap();
+        std::fs::write(
+            dir.join(".cargo").join("config.toml"),
+            "[alias]\nxtask = \"run --package xtask --\"\nt = [\"test\", \"--all\"]\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("pyproject.toml"),
+            "[tool.poetry]\nname = \"lp\"\n\n[tool.poetry.scripts]\nlp = \"lp.cli:main\"\n",
+        )
+        .unwrap();
+
+        let tasks = discover(&dir);
+
+        let dev = tasks.iter().find(|t| t.id == "Npm:dev").unwrap();
+
+        assert_eq!(dev.command, ["pnpm", "run", "dev"]);
+        assert_eq!(dev.description.as_deref(), Some("vite"));
+
+        let cargo: Vec<&str> = tasks
+            .iter()
+            .filter(|t| t.source == TaskSource::Cargo)
+            .map(|t| t.name.as_str())
+            .collect();
+
+        assert_eq!(
+            cargo,
+            ["build", "check", "test", "run", "clippy", "fmt", "doc"]
+        );
+
+        let alias = tasks.iter().find(|t| t.id == "CargoAlias:t").unwrap();
+
+        assert_eq!(alias.command, ["cargo", "t"]);
+        assert_eq!(alias.description.as_deref(), Some("test --all"));
+
+        let script = tasks.iter().find(|t| t.id == "Poetry:lp").unwrap();
+
+        assert_eq!(script.command, ["poetry", "run", "lp"]);
+
+        assert!(!tasks.iter().any(|t| t.source == TaskSource::Flutter));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,51 @@
+// Runnable tasks of a project, read from its manifests and task files
+
+use serde::{Deserialize, Serialize};
+
+pub mod discover;
+pub mod runner;
+
+/// Where a task was found
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum TaskSource {
+    /// `scripts` of package.json
+    Npm,
+    /// Standard cargo commands
+    Cargo,
+    /// `[alias]` of `.cargo/config.toml`
+    CargoAlias,
+    Make,
+    Just,
+    Flutter,
+    Poetry,
+}
+
+/// A command run in the project dir
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Task {
+    /// Unique within a project, e.g. `Npm:build`
+    pub id: String,
+    pub name: String,
+    pub source: TaskSource,
+    /// Program and its arguments
+    pub command: Vec<String>,
+    /// Script, alias or doc comment of the task, if any
+    pub description: Option<String>,
+}
+
+impl Task {
+    pub fn new(
+        source: TaskSource,
+        name: &str,
+        command: &[&str],
+        description: Option<String>,
+    ) -> Self {
+        Task {
+            id: format!("{source:?}:{name}"),
+            name: name.to_string(),
+            source,
+            command: command.iter().map(|c| c.to_string()).collect(),
+            description,
+        }
+    }
+}
@@ -0,0 +1,393 @@
+// Runs tasks in the background, streaming their output line by line
+
+use std::{
+    collections::HashMap,
+    io::{BufRead, BufReader, Read},
+    path::Path,
+    process::{Child, Command, Stdio},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+use serde::Serialize;
+
+use crate::{errors::lp_error::LpError, launcher::find_program};
+
+use super::Task;
+
+/// Event emitted to the frontend for each line a task prints
+pub const TASK_OUTPUT_EVENT: &str = "task-output";
+
+/// Event emitted to the frontend once a task exited
+pub const TASK_EXITED_EVENT: &str = "task-exited";
+
+/// How often a running task is checked for its exit
+const EXIT_POLL: Duration = Duration::from_millis(50);
+
+/// How long the output of an exited task is still read before its exit is
+/// emitted, a process it left running may keep the output open for longer
+const DRAIN_TIMEOUT: Duration = Duration::from_millis(500);
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputStream {
+    Stdout,
+    Stderr,
+}
+
+/// Output and exit of a running task, payloads of
+/// `TASK_OUTPUT_EVENT` and `TASK_EXITED_EVENT`
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(untagged)]
+pub enum TaskEvent {
+    Output {
+        run_id: u64,
+        stream: OutputStream,
+        /// Without the line break, invalid UTF-8 is replaced
+        line: String,
+    },
+    Exited {
+        run_id: u64,
+        /// `None` if the task was ended by a signal
+        code: Option<i32>,
+        cancelled: bool,
+    },
+}
+
+impl TaskEvent {
+    /// Name of the event to emit
+    pub fn name(&self) -> &'static str {
+        match self {
+            TaskEvent::Output { .. } => TASK_OUTPUT_EVENT,
+            TaskEvent::Exited { .. } => TASK_EXITED_EVENT,
+        }
+    }
+}
+
+struct RunningTask {
+    child: Child,
+    cancelled: bool,
+}
+
+/// Tasks started from the app, by run id
+#[derive(Default)]
+pub struct TaskRunner {
+    next_id: AtomicU64,
+    runs: Arc<Mutex<HashMap<u64, RunningTask>>>,
+}
+
+impl TaskRunner {
+    /// Starts `task` in `dir` and returns its run id
+    /// `on_event` gets every line of its output, then its exit
+    /// Lines of processes it left running may still follow the exit
+    pub fn start(
+        &self,
+        task: &Task,
+        dir: &Path,
+        on_event: impl Fn(TaskEvent) + Send + Sync + 'static,
+    ) -> Result<u64, LpError> {
+        let (program, args) = task
+            .command
+            .split_first()
+            .ok_or(LpError::Error(format!("Task {} has no command", task.id)))?;
+
+        let program = find_program(program).ok_or(LpError::Error(format!(
+            "{program} not found, is it installed?"
+        )))?;
+
+        let mut command = Command::new(program);
+
+        command
+            .args(args)
+            .current_dir(dir)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+
+        // its own process group, so cancelling also ends what it started
+        #[cfg(unix)]
+        std::os::unix::process::CommandExt::process_group(&mut command, 0);
+
+        let mut child = command.spawn().map_err(|err| {
+            LpError::Error(format!("Cannot run {}: {err}", task.command.join(" ")))
+        })?;
+
+        let run_id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+
+        eprintln!("[tasks] Started {} in {dir:?} as {run_id}", task.id);
+
+        let on_event = Arc::new(on_event);
+
+        let readers =
+            [
+                child.stdout.take().map(|out| {
+                    read_lines(out, run_id, OutputStream::Stdout, Arc::clone(&on_event))
+                }),
+                child.stderr.take().map(|err| {
+                    read_lines(err, run_id, OutputStream::Stderr, Arc::clone(&on_event))
+                }),
+            ];
+
+        self.runs.lock().unwrap().insert(
+            run_id,
+            RunningTask {
+                child,
+                cancelled: false,
+            },
+        );
+
+        let runs = Arc::clone(&self.runs);
+
+        std::thread::spawn(move || {
+            // the lock is only held briefly, so `cancel` is never blocked
+            let (code, cancelled) = loop {
+                let mut runs = runs.lock().unwrap();
+
+                let Some(run) = runs.get_mut(&run_id) else {
+                    return;
+                };
+
+                match run.child.try_wait() {
+                    Ok(None) => {}
+                    Ok(Some(status)) => break (status.code(), run.cancelled),
+                    Err(err) => {
+                        eprintln!("ERROR: waiting for task {run_id} : {err}");
+                        break (None, run.cancelled);
+                    }
+                }
+
+                drop(runs);
+
+                std::thread::sleep(EXIT_POLL);
+            };
+
+            runs.lock().unwrap().remove(&run_id);
+
+            // lines printed just before the exit come first, without
+            // waiting on whatever still holds the output open
+            let drained_by = Instant::now() + DRAIN_TIMEOUT;
+
+            for reader in readers.iter().flatten() {
+                while !reader.is_finished() && Instant::now() < drained_by {
+                    std::thread::sleep(Duration::from_millis(10));
+                }
+            }
+
+            on_event(TaskEvent::Exited {
+                run_id,
+                code,
+                cancelled,
+            });
+        });
+
+        Ok(run_id)
+    }
+
+    /// Ends the task `run_id` with everything it started
+    pub fn cancel(&self, run_id: u64) -> Result<(), LpError> {
+        let mut runs = self.runs.lock().unwrap();
+
+        let run = runs
+            .get_mut(&run_id)
+            .ok_or(LpError::Error(format!("No running task {run_id}")))?;
+
+        run.cancelled = true;
+
+        let pid = run.child.id().to_string();
+
+        // `kill` of `Child` only ends the direct child, e.g. npm but not node
+        let tree_killed = if cfg!(windows) {
+            Command::new("taskkill")
+                .args(["/T", "/F", "/PID", &pid])
+                .status()
+        } else {
+            Command::new("kill")
+                .args(["-TERM", "--", &format!("-{pid}")])
+                .status()
+        };
+
+        if !tree_killed.map(|status| status.success()).unwrap_or(false) {
+            run.child.kill()?;
+        }
+
+        eprintln!("[tasks] Cancelled {run_id}");
+
+        Ok(())
+    }
+
+    /// Run ids of the tasks still running
+    pub fn running(&self) -> Vec<u64> {
+        let mut ids: Vec<u64> = self.runs.lock().unwrap().keys().copied().collect();
+
+        ids.sort_unstable();
+
+        ids
+    }
+}
+
+/// Sends each line of `output` to `on_event` until it closes
+fn read_lines(
+    output: impl Read + Send + 'static,
+    run_id: u64,
+    stream: OutputStream,
+    on_event: Arc<impl Fn(TaskEvent) + Send + Sync + 'static>,
+) -> JoinHandle<()> {
+    std::thread::spawn(move || {
+        let mut reader = BufReader::new(output);
+        let mut buf = Vec::new();
+
+        while let Ok(read) = reader.read_until(b'\n', &mut buf) {
+            if read == 0 {
+                break;
+            }
+
+            let line = String::from_utf8_lossy(&buf);
+
+            on_event(TaskEvent::Output {
+                run_id,
+                stream,
+                line: line.trim_end_matches(['\n', '\r']).to_string(),
+            });
+
+            buf.clear();
+        }
+    })
+}
+
+#[cfg(all(test, unix))]
+mod runner_tests {
+    use std::{
+        sync::{mpsc, Mutex},
+        time::Duration,
+    };
+
+    use super::{OutputStream, TaskEvent, TaskRunner};
+    use crate::tasks::{Task, TaskSource};
+
+    fn shell_task(script: &str) -> Task {
+        Task::new(TaskSource::Make, "script", &["sh", "-c", script], None)
+    }
+
+    fn forward(sender: &mpsc::Sender<TaskEvent>) -> impl Fn(TaskEvent) + Send + Sync + 'static {
+        let sender = Mutex::new(sender.clone());
+
+        move |event| {
+            let _ = sender.lock().unwrap().send(event);
+        }
+    }
+
+    #[test]
+    fn it_should_stream_output_and_cancel_tasks() {
+        let runner = TaskRunner::default();
+        let dir = std::env::temp_dir();
+
+        let (sender, receiver) = mpsc::channel();
+
+        let run_id = runner
+            .start(
+                &shell_task("echo out; echo err >&2; exit 3"),
+                &dir,
+                forward(&sender),
+            )
+            .unwrap();
+
+        let mut events = Vec::new();
+
+        while let Ok(event) = receiver.recv_timeout(Duration::from_secs(5)) {
+            let exited = matches!(event, TaskEvent::Exited { .. });
+
+            events.push(event);
+
+            if exited {
+                break;
+            }
+        }
+
+        assert!(events.contains(&TaskEvent::Output {
+            run_id,
+            stream: OutputStream::Stdout,
+            line: String::from("out"),
+        }));
+        assert!(events.contains(&TaskEvent::Output {
+            run_id,
+            stream: OutputStream::Stderr,
+            line: String::from("err"),
+        }));
+        assert_eq!(
+            events.last(),
+            Some(&TaskEvent::Exited {
+                run_id,
+                code: Some(3),
+                cancelled: false,
+            })
+        );
+
+        let run_id = runner
+            .start(&shell_task("sleep 30 & wait"), &dir, forward(&sender))
+            .unwrap();
+
+        assert_eq!(runner.running(), vec![run_id]);
+
+        runner.cancel(run_id).unwrap();
+
+        let exited = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+
+        assert!(matches!(
+            exited,
+            TaskEvent::Exited {
+                cancelled: true,
+                ..
+            }
+        ));
+        assert!(runner.running().is_empty());
+        assert!(runner.cancel(run_id).is_err());
+    }
+
+    #[test]
+    fn it_should_exit_while_a_started_process_holds_the_output() {
+        let runner = TaskRunner::default();
+
+        let (sender, receiver) = mpsc::channel();
+
+        let run_id = runner
+            .start(
+                &shell_task("sleep 5 & echo started"),
+                &std::env::temp_dir(),
+                forward(&sender),
+            )
+            .unwrap();
+
+        let mut events = Vec::new();
+
+        while let Ok(event) = receiver.recv_timeout(Duration::from_secs(3)) {
+            let exited = matches!(event, TaskEvent::Exited { .. });
+
+            events.push(event);
+
+            if exited {
+                break;
+            }
+        }
+
+        assert_eq!(
+            events,
+            vec![
+                TaskEvent::Output {
+                    run_id,
+                    stream: OutputStream::Stdout,
+                    line: String::from("started"),
+                },
+                TaskEvent::Exited {
+                    run_id,
+                    code: Some(0),
+                    cancelled: false,
+                },
+            ]
+        );
+        assert!(runner.running().is_empty());
+    }
+}
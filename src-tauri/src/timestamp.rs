@@ -0,0 +1,101 @@
+// Portable timestamps for everything persisted to the config dir
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+/// Milliseconds since the unix epoch, negative before it
+/// `SystemTime` serializes as its platform fields and its precision differs
+/// between OSes, a `Timestamp` means the same on every machine
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(from = "StoredTimestamp")]
+pub struct Timestamp(i64);
+
+/// Timestamps as stored by older versions, `SystemTime` was
+/// serialized as `{ "secs_since_epoch": .., "nanos_since_epoch": .. }`
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredTimestamp {
+    Millis(i64),
+    SystemTime {
+        secs_since_epoch: u64,
+        nanos_since_epoch: u32,
+    },
+}
+
+impl From<StoredTimestamp> for Timestamp {
+    fn from(stored: StoredTimestamp) -> Self {
+        match stored {
+            StoredTimestamp::Millis(millis) => Timestamp(millis),
+            StoredTimestamp::SystemTime {
+                secs_since_epoch,
+                nanos_since_epoch,
+            } => Timestamp::from(UNIX_EPOCH + Duration::new(secs_since_epoch, nanos_since_epoch)),
+        }
+    }
+}
+
+impl Timestamp {
+    pub fn now() -> Self {
+        Timestamp::from(SystemTime::now())
+    }
+
+    pub fn from_millis(millis: i64) -> Self {
+        Timestamp(millis)
+    }
+
+    pub fn as_millis(self) -> i64 {
+        self.0
+    }
+}
+
+/// Truncates to whole milliseconds
+impl From<SystemTime> for Timestamp {
+    fn from(time: SystemTime) -> Self {
+        match time.duration_since(UNIX_EPOCH) {
+            Ok(since) => Timestamp(since.as_millis() as i64),
+            Err(err) => Timestamp(-(err.duration().as_millis() as i64)),
+        }
+    }
+}
+
+impl From<Timestamp> for SystemTime {
+    fn from(timestamp: Timestamp) -> Self {
+        let millis = Duration::from_millis(timestamp.0.unsigned_abs());
+
+        if timestamp.0 >= 0 {
+            UNIX_EPOCH + millis
+        } else {
+            UNIX_EPOCH - millis
+        }
+    }
+}
+
+#[cfg(test)]
+mod timestamp_tests {
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    use super::Timestamp;
+
+    #[test]
+    fn it_should_read_legacy_system_times_and_round_trip_millis() {
+        let legacy = r#"{ "secs_since_epoch": 1700000000, "nanos_since_epoch": 123456789 }"#;
+
+        let timestamp: Timestamp = serde_json::from_str(legacy).unwrap();
+
+        assert_eq!(timestamp.as_millis(), 1_700_000_000_123);
+        assert_eq!(serde_json::to_string(&timestamp).unwrap(), "1700000000123");
+
+        let read: Timestamp = serde_json::from_str("1700000000123").unwrap();
+
+        assert_eq!(read, timestamp);
+
+        let before_epoch = UNIX_EPOCH - Duration::from_millis(1500);
+
+        assert_eq!(Timestamp::from(before_epoch).as_millis(), -1500);
+        assert_eq!(
+            SystemTime::from(Timestamp::from(before_epoch)),
+            before_epoch
+        );
+    }
+}
@@ -0,0 +1,537 @@
+// Watches `project_dirs` and re-indexes only the affected projects
+
+use std::{
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use crossbeam_channel::{Receiver, RecvTimeoutError};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+
+use crate::{
+    config::Config,
+    dir_walker::{
+        walk_filter::{IgnoreStack, WalkFilter},
+        walker,
+    },
+    errors::lp_error::LpError,
+    indexer::Index,
+    search::{add_project_document, remove_project_document, save_model},
+    search_model::Model,
+};
+
+/// Event emitted to the frontend after the watcher updated the index
+pub const INDEX_UPDATED_EVENT: &str = "index-updated";
+
+/// Events arriving within this window are handled as one batch
+const DEBOUNCE: Duration = Duration::from_millis(1500);
+
+/// Payload for `INDEX_UPDATED_EVENT`
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct IndexUpdate {
+    /// Paths of projects that were added or re-indexed
+    pub updated: Vec<String>,
+    /// Paths of projects that were removed from the index
+    pub removed: Vec<String>,
+}
+
+/// Background watcher over `Config::project_dirs`
+/// Dropping it stops watching
+pub struct ProjectWatcher {
+    watches: Arc<Mutex<Watches>>,
+    index: Arc<Mutex<Index>>,
+}
+
+/// The notify watcher and the dirs it watches, shared with the event thread
+/// Every dir is watched non recursively, so build output, dependencies and
+/// sources inside projects don't use up the OS's watch limit
+struct Watches {
+    watcher: RecommendedWatcher,
+    dirs: BTreeSet<PathBuf>,
+}
+
+impl ProjectWatcher {
+    /// Starts watching all `project_dirs` in `config`
+    /// Changes are applied to `index` and `model` on a background thread
+    pub fn start(
+        app: AppHandle,
+        config: Arc<Mutex<Config>>,
+        index: Arc<Mutex<Index>>,
+        model: Arc<Mutex<Model>>,
+    ) -> Result<Self, LpError> {
+        let (event_sender, event_receiver) = crossbeam_channel::unbounded();
+
+        let watcher = notify::recommended_watcher(move |res| {
+            let _ = event_sender.send(res);
+        })
+        .map_err(|err| LpError::Error(format!("cannot start watcher: {err}")))?;
+
+        let project_watcher = ProjectWatcher {
+            watches: Arc::new(Mutex::new(Watches {
+                watcher,
+                dirs: BTreeSet::new(),
+            })),
+            index: Arc::clone(&index),
+        };
+
+        let current = config.lock().unwrap().clone();
+        project_watcher.sync(&current);
+
+        let watches = Arc::clone(&project_watcher.watches);
+
+        std::thread::spawn(move || {
+            handle_events(event_receiver, app, config, index, model, watches);
+        });
+
+        Ok(project_watcher)
+    }
+
+    /// Updates the watched dirs after `project_dirs` or the walk globs
+    /// in `config` changed
+    pub fn sync(&self, config: &Config) {
+        let index = self.index.lock().unwrap();
+
+        self.watches.lock().unwrap().sync(config, &index);
+    }
+}
+
+impl Watches {
+    /// Watches the dirs the walker would visit, see `watched_dirs`,
+    /// and stops watching the others
+    fn sync(&mut self, config: &Config, index: &Index) {
+        let wanted = watched_dirs(config, index);
+
+        for dir in self.dirs.difference(&wanted) {
+            // a removed dir is already unwatched by the OS
+            let _ = self.watcher.unwatch(dir);
+        }
+
+        self.dirs.retain(|dir| wanted.contains(dir));
+
+        for dir in wanted {
+            if self.dirs.contains(&dir) {
+                continue;
+            }
+
+            match self.watcher.watch(&dir, RecursiveMode::NonRecursive) {
+                Ok(()) => {
+                    self.dirs.insert(dir);
+                }
+                Err(err) => eprintln!("ERROR: cannot watch {dir:?} : {err}"),
+            }
+        }
+    }
+}
+
+/// Collects debounced batches of events and re-indexes the affected dirs
+fn handle_events(
+    event_receiver: Receiver<notify::Result<Event>>,
+    app: AppHandle,
+    config: Arc<Mutex<Config>>,
+    index: Arc<Mutex<Index>>,
+    model: Arc<Mutex<Model>>,
+    watches: Arc<Mutex<Watches>>,
+) {
+    // Blocks until the first event of a batch
+    while let Ok(first) = event_receiver.recv() {
+        let mut paths = BTreeSet::new();
+
+        let mut collect = |res: notify::Result<Event>| match res {
+            Ok(event) => {
+                if !matches!(event.kind, EventKind::Access(_)) {
+                    paths.extend(event.paths);
+                }
+            }
+            Err(err) => eprintln!("ERROR: watcher : {err}"),
+        };
+
+        collect(first);
+
+        loop {
+            match event_receiver.recv_timeout(DEBOUNCE) {
+                Ok(res) => collect(res),
+                Err(RecvTimeoutError::Timeout) => break,
+                Err(RecvTimeoutError::Disconnected) => return,
+            }
+        }
+
+        let config = config.lock().unwrap().clone();
+
+        let dirs = {
+            let index = index.lock().unwrap();
+            affected_dirs(&paths, &config, &index)
+        };
+
+        let update = if dirs.is_empty() {
+            IndexUpdate::default()
+        } else {
+            reindex_dirs(&dirs, &index, &model)
+        };
+
+        // created and removed dirs and projects change what is watched
+        watches
+            .lock()
+            .unwrap()
+            .sync(&config, &index.lock().unwrap());
+
+        if update.updated.is_empty() && update.removed.is_empty() {
+            continue;
+        }
+
+        eprintln!("[watcher] Index updated {update:?}");
+
+        if let Err(err) = index.lock().unwrap().save() {
+            eprintln!("ERROR: saving index : {err}");
+        }
+
+        let _ = save_model(&model.lock().unwrap());
+
+        if let Err(err) = app.emit_all(INDEX_UPDATED_EVENT, update) {
+            eprintln!("ERROR: emitting {INDEX_UPDATED_EVENT} : {err}");
+        }
+    }
+}
+
+/// Maps changed paths to the directories that need re-indexing
+/// * inside an indexed project, only the project dir itself or one of its
+///   top level project files (manifest, README) maps to the project's root
+/// * any other path maps to the directory it is in, a candidate new project
+///
+/// Paths outside the configured roots, or in dirs the walker skips, are dropped
+fn affected_dirs(paths: &BTreeSet<PathBuf>, config: &Config, index: &Index) -> Vec<PathBuf> {
+    let filter = WalkFilter::new(config);
+
+    let mut dirs = BTreeSet::new();
+
+    for path in paths {
+        let Some(root) = config.project_dirs.iter().find(|r| path.starts_with(r)) else {
+            continue;
+        };
+
+        // a removed dir may have held indexed projects
+        if !path.exists() {
+            dirs.extend(
+                index
+                    .projects_map()
+                    .keys()
+                    .filter(|p| p.starts_with(path))
+                    .cloned(),
+            );
+        }
+
+        let dir = if path.is_dir() {
+            path.to_path_buf()
+        } else {
+            match path.parent() {
+                Some(parent) => parent.to_path_buf(),
+                None => continue,
+            }
+        };
+
+        if is_skipped(&filter, root, &dir) {
+            continue;
+        }
+
+        let project_root = index
+            .projects_map()
+            .keys()
+            .filter(|p| path.starts_with(p))
+            .max_by_key(|p| p.components().count());
+
+        if let Some(project_root) = project_root {
+            let is_project_file = path.parent() == Some(project_root.as_path())
+                && path
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(walker::is_project_file);
+
+            if path == project_root || is_project_file {
+                dirs.insert(project_root.to_path_buf());
+            }
+
+            continue;
+        }
+
+        if dir != *root {
+            dirs.insert(dir);
+        }
+    }
+
+    dirs.into_iter().collect()
+}
+
+/// Dirs the walker visits under `project_dirs`, up to `walker::MAX_DEPTH`
+/// Indexed projects are watched but not descended into,
+/// only their top level files are part of their details
+fn watched_dirs(config: &Config, index: &Index) -> BTreeSet<PathBuf> {
+    let filter = WalkFilter::new(config);
+
+    let mut dirs = BTreeSet::new();
+
+    let mut pending: Vec<(PathBuf, usize, IgnoreStack)> = config
+        .project_dirs
+        .iter()
+        .map(|root| (root.to_path_buf(), 0, Arc::new(Vec::new())))
+        .collect();
+
+    while let Some((dir, depth, stack)) = pending.pop() {
+        let Ok(read_dir) = dir.read_dir() else {
+            continue;
+        };
+
+        dirs.insert(dir.clone());
+
+        if depth >= walker::MAX_DEPTH || index.projects_map().contains_key(&dir) {
+            continue;
+        }
+
+        let stack = filter.stack_for(&dir, &stack);
+
+        for child in read_dir.filter_map(|it| it.ok().map(|it| it.path())) {
+            let dot_dir = child
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with('.'));
+
+            if !child.is_dir() || dot_dir || filter.is_excluded(&child, &stack) {
+                continue;
+            }
+
+            pending.push((child, depth + 1, Arc::clone(&stack)));
+        }
+    }
+
+    dirs
+}
+
+/// Whether the walker skips `dir`, or one of its ancestors below `root`
+fn is_skipped(filter: &WalkFilter, root: &Path, dir: &Path) -> bool {
+    let Ok(relative) = dir.strip_prefix(root) else {
+        return true;
+    };
+
+    let mut stack = filter.stack_for(root, &Arc::new(Vec::new()));
+    let mut current = root.to_path_buf();
+
+    for component in relative.components() {
+        current.push(component);
+
+        let dot_dir = current
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.starts_with('.'));
+
+        if dot_dir || filter.is_excluded(&current, &stack) {
+            return true;
+        }
+
+        stack = filter.stack_for(&current, &stack);
+    }
+
+    false
+}
+
+/// Re-detects the project in each of `dirs`, sorted parents first
+/// A dir inside a project detected in this batch is skipped
+fn reindex_dirs(
+    dirs: &[PathBuf],
+    index: &Arc<Mutex<Index>>,
+    model: &Arc<Mutex<Model>>,
+) -> IndexUpdate {
+    let mut update = IndexUpdate::default();
+
+    let mut detected: Vec<&PathBuf> = Vec::new();
+
+    for dir in dirs {
+        if detected.iter().any(|d| dir.starts_with(d)) {
+            continue;
+        }
+
+        let project = if dir.is_dir() {
+            walker::analyze_dir(dir)
+        } else {
+            None
+        };
+
+        // same order as `search_query`, model then index
+        let mut model = model.lock().unwrap();
+        let mut index = index.lock().unwrap();
+
+        match project {
+            Some(project) => {
+                if let Some(old) = index.projects_map().get(dir) {
+                    remove_project_document(&mut model, old);
+                }
+
+                add_project_document(&mut model, &project);
+                index.add_project(dir, project);
+
+                update.updated.push(dir.to_string_lossy().to_string());
+                detected.push(dir);
+            }
+            None => {
+                if let Some(old) = index.remove_project(dir) {
+                    remove_project_document(&mut model, &old);
+
+                    update.removed.push(dir.to_string_lossy().to_string());
+                }
+            }
+        }
+    }
+
+    update
+}
+
+#[cfg(test)]
+mod watcher_tests {
+    use std::{
+        collections::BTreeSet,
+        path::PathBuf,
+        sync::{Arc, Mutex},
+    };
+
+    use super::{affected_dirs, reindex_dirs, watched_dirs};
+    use crate::{
+        config::Config, dir_walker::walker, file_handler, indexer::Index, search_model::Model,
+    };
+
+    #[test]
+    fn it_should_only_reindex_projects_whose_details_changed() {
+        let root = std::env::temp_dir().join("lp_watcher_affected_test");
+        let _ = std::fs::remove_dir_all(&root);
+
+        let app = root.join("app");
+        let web = root.join("web");
+
+        for dir in [
+            app.join("src"),
+            app.join("target/debug"),
+            web.join("node_modules/dep"),
+        ] {
+            std::fs::create_dir_all(dir).unwrap();
+        }
+
+        std::fs::write(app.join("Cargo.toml"), "[package]\nname = \"app\"\n").unwrap();
+        std::fs::write(app.join("README.md"), "# App\n").unwrap();
+        std::fs::write(app.join("src/main.rs"), "fn main() {}\n").unwrap();
+        std::fs::write(app.join("target/debug/app"), "").unwrap();
+        std::fs::write(web.join("package.json"), "{}").unwrap();
+        std::fs::write(web.join("node_modules/dep/package.json"), "{}").unwrap();
+
+        let mut config = Config::new();
+        config.project_dirs = vec![root.clone()];
+
+        let mut index = Index::default();
+        index.add_project(&app, walker::analyze_dir(&app).unwrap());
+
+        let changed = |paths: Vec<PathBuf>| {
+            let paths: BTreeSet<PathBuf> = paths.into_iter().collect();
+            affected_dirs(&paths, &config, &index)
+        };
+
+        assert!(changed(vec![
+            app.join("src/main.rs"),
+            app.join("target/debug/app"),
+            web.join("node_modules/dep/package.json"),
+        ])
+        .is_empty());
+
+        assert_eq!(
+            changed(vec![app.join("README.md"), web.join("package.json")]),
+            vec![app.clone(), web.clone()]
+        );
+
+        std::fs::remove_dir_all(&app).unwrap();
+
+        assert_eq!(changed(vec![app.clone()]), vec![app.clone()]);
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn it_should_keep_a_re_index_when_updating_the_index() {
+        let root = std::env::temp_dir().join("lp_watcher_re_index_test");
+        let _ = std::fs::remove_dir_all(&root);
+
+        let app = root.join("app");
+        let lib = root.join("lib");
+
+        for (dir, name) in [(&app, "app"), (&lib, "lib")] {
+            std::fs::create_dir_all(dir).unwrap();
+            std::fs::write(
+                dir.join("Cargo.toml"),
+                format!("[package]\nname = \"{name}\"\n"),
+            )
+            .unwrap();
+        }
+
+        let mut config = Config::new();
+        config.project_dirs = vec![root.clone()];
+
+        // the app's index and model, as shared with the watcher
+        let index = Arc::new(Mutex::new(Index::default()));
+        let model = Arc::new(Mutex::new(Model::default()));
+
+        let pruned = file_handler::index_projects(&config, &index);
+
+        assert!(pruned.is_empty());
+
+        std::fs::write(lib.join("README.md"), "# Lib\n").unwrap();
+
+        let update = reindex_dirs(std::slice::from_ref(&lib), &index, &model);
+
+        assert_eq!(update.updated, vec![lib.to_string_lossy().to_string()]);
+
+        let indexed: Vec<PathBuf> = index
+            .lock()
+            .unwrap()
+            .projects_map()
+            .keys()
+            .cloned()
+            .collect();
+
+        assert_eq!(indexed, vec![app, lib]);
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn it_should_watch_the_dirs_the_walker_visits() {
+        let root = std::env::temp_dir().join("lp_watcher_watched_dirs_test");
+        let _ = std::fs::remove_dir_all(&root);
+
+        let app = root.join("app");
+        let group = root.join("group");
+
+        for dir in [
+            app.join("src/bin"),
+            app.join("target/debug"),
+            group.join("node_modules/dep"),
+            group.join(".cache"),
+            group.join("empty"),
+        ] {
+            std::fs::create_dir_all(dir).unwrap();
+        }
+
+        std::fs::write(app.join("Cargo.toml"), "[package]\nname = \"app\"\n").unwrap();
+
+        let mut config = Config::new();
+        config.project_dirs = vec![root.clone()];
+
+        let mut index = Index::default();
+        index.add_project(&app, walker::analyze_dir(&app).unwrap());
+
+        let watched: Vec<PathBuf> = watched_dirs(&config, &index).into_iter().collect();
+
+        assert_eq!(
+            watched,
+            vec![root.clone(), app, group.clone(), group.join("empty")]
+        );
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
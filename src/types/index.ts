@@ -1,5 +1,7 @@
 export type Config = {
   project_dirs: string[];
+  exclude_globs: string[];
+  include_globs: string[];
 };
 
 export interface IProject {
@@ -9,13 +11,95 @@ export interface IProject {
   description?: string;
   language_map: Map<string, number>;
   project_type: ProjectType;
-  last_modified: { secs_since_epoch: number };
+  /** Milliseconds since the unix epoch */
+  last_modified: number;
+  documentation_file?: string;
+  members: Array<IWorkspaceMember>;
+  git_status?: IGitStatus;
+  tasks: Array<ITask>;
+}
+
+export interface ITask {
+  /** Unique within a project, e.g. `Npm:build` */
+  id: string;
+  name: string;
+  source: "Npm" | "Cargo" | "CargoAlias" | "Make" | "Just" | "Flutter" | "Poetry";
+  command: Array<string>;
+  description?: string;
+}
+
+/** Payload of the `task-output` event */
+export interface ITaskOutput {
+  run_id: number;
+  stream: "stdout" | "stderr";
+  line: string;
+}
+
+/** Payload of the `task-exited` event */
+export interface ITaskExit {
+  run_id: number;
+  code: number | null;
+  cancelled: boolean;
+}
+
+export interface ISearchHit extends IProject {
+  score?: number;
+  matched_fields: Array<string>;
+  snippets: Array<ISnippet>;
+}
+
+export interface ISnippet {
+  field: string;
+  text: string;
+  /** Char offsets of the matched terms in `text` */
+  highlights: Array<{ start: number; end: number }>;
+}
+
+export interface ISearchResponse {
+  projects: Array<ISearchHit>;
+  suggestion?: string;
+  facets: IFacets;
+}
+
+export interface IFacets {
+  project_types: Record<string, number>;
+  languages: Record<string, number>;
+}
+
+export interface IGitStatus {
+  head: { Branch: string } | { Detached: string } | { Unborn: string };
+  staged: number;
+  unstaged: number;
+  untracked: number;
+  conflicted: number;
+  upstream?: string;
+  upstream_url?: string;
+  upstream_branch?: string;
+  ahead: number;
+  behind: number;
+  last_commit?: {
+    hash: string;
+    summary?: string;
+    author?: string;
+    time: number;
+  };
+  stash_count: number;
+}
+
+export interface IWorkspaceMember {
+  name: string;
+  path: string;
+  description?: string;
+  project_type: ProjectType;
   documentation_file?: string;
 }
 
 export enum ProjectType {
   Rust,
+  Go,
   Python,
+  Java,
+  Kotlin,
   Flutter,
   Ruby,
   NextJs,